@@ -0,0 +1,137 @@
+/* good_stv - a good single transferable vote utility.
+ * Copyright (C) 2019 good_stv authors
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>. */
+
+//! Benchmarks for parsing and counting synthetic elections.
+//!
+//! Run with `cargo bench`.
+
+use std::fmt;
+use std::io::Cursor;
+
+use criterion::{criterion_group, criterion_main, Criterion, ParameterizedBenchmark, Throughput};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use good_stv::{BallotBox, Counter, ElectionConfig, Method};
+
+/// Shape of a synthetic election.
+#[derive(Clone, Copy)]
+struct Size {
+    candidates: usize,
+    seats: u64,
+    ballots: usize,
+}
+
+impl fmt::Debug for Size {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}c/{}s/{}b", self.candidates, self.seats, self.ballots)
+    }
+}
+
+const SIZES: &[Size] = &[
+    Size {
+        candidates: 5,
+        seats: 1,
+        ballots: 1_000,
+    },
+    Size {
+        candidates: 10,
+        seats: 3,
+        ballots: 10_000,
+    },
+    Size {
+        candidates: 20,
+        seats: 5,
+        ballots: 100_000,
+    },
+];
+
+/// Generate a CSV election in which candidates with lower numbers are more popular, and each
+/// ballot ranks a random number of them.
+fn synthetic_csv(size: Size) -> String {
+    let mut rng = StdRng::seed_from_u64(size.ballots as u64);
+    let candidates: Vec<String> = (0..size.candidates).map(|i| format!("c{}", i)).collect();
+    let mut csv = candidates.join(",");
+    csv.push('\n');
+
+    let mut ranking: Vec<&str> = candidates.iter().map(String::as_str).collect();
+    for _ in 0..size.ballots {
+        ranking.shuffle(&mut rng);
+        // Nudge the favourite towards the front so that the count has surpluses to transfer.
+        let favourite = rng
+            .gen_range(0, size.candidates)
+            .min(rng.gen_range(0, size.candidates));
+        let position = ranking
+            .iter()
+            .position(|&c| c == candidates[favourite])
+            .unwrap();
+        ranking.swap(0, position);
+        let length = rng.gen_range(1, size.candidates + 1);
+        csv.push_str(&ranking[..length].join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+fn parsing(c: &mut Criterion) {
+    let inputs: Vec<(Size, String)> = SIZES.iter().map(|&s| (s, synthetic_csv(s))).collect();
+    c.bench(
+        "from_reader",
+        ParameterizedBenchmark::new(
+            "csv",
+            |b, (_, csv)| b.iter(|| BallotBox::from_reader(Cursor::new(csv.as_bytes())).unwrap()),
+            inputs.clone(),
+        )
+        // The largest elections take a while to parse, so keep the run time reasonable.
+        .sample_size(20)
+        .throughput(|(_, csv)| Throughput::Bytes(csv.len() as u32)),
+    );
+}
+
+fn counting(c: &mut Criterion) {
+    let inputs: Vec<(Size, BallotBox)> = SIZES
+        .iter()
+        .map(|&s| {
+            let csv = synthetic_csv(s);
+            (s, BallotBox::from_reader(Cursor::new(csv)).unwrap())
+        })
+        .collect();
+    c.bench(
+        "results",
+        ParameterizedBenchmark::new(
+            "random",
+            |b, (size, ballot_box)| {
+                let config = ElectionConfig::new(size.seats).seed(0);
+                let counter = Counter::new(ballot_box, config).unwrap();
+                b.iter(|| counter.results().unwrap())
+            },
+            inputs,
+        )
+        .with_function("gregory", |b, (size, ballot_box)| {
+            let config = ElectionConfig::new(size.seats)
+                .method(Method::Gregory)
+                .seed(0);
+            let counter = Counter::new(ballot_box, config).unwrap();
+            b.iter(|| counter.results().unwrap())
+        })
+        .sample_size(20)
+        .throughput(|(size, _)| Throughput::Elements(size.ballots as u32)),
+    );
+}
+
+criterion_group!(benches, parsing, counting);
+criterion_main!(benches);
@@ -0,0 +1,225 @@
+/* good_stv - a good single transferable vote utility.
+ * Copyright (C) 2019 good_stv authors
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>. */
+
+//! Statistics about a set of ballots, independent of any count.
+
+use std::io::Write;
+
+use failure::*;
+
+use crate::{BallotBox, Candidate};
+
+/// How the voters ranked the candidates, worked out from the ballots alone.
+///
+/// Only running candidates are considered. Names which aren't running are skipped, as are repeated
+/// preferences for the same candidate.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Analysis {
+    candidates: Vec<Candidate>,
+    num_ballots: u64,
+    num_preferences: u64,
+    first_preferences: Vec<u64>,
+    pairwise: Vec<Vec<u64>>,
+}
+
+impl Analysis {
+    /// Analyse every ballot in `ballot_box`.
+    pub fn new(ballot_box: &BallotBox) -> Self {
+        let num_candidates = ballot_box.candidates().len();
+        let mut analysis = Analysis {
+            candidates: ballot_box.candidates().to_vec(),
+            num_ballots: ballot_box.num_votes(),
+            num_preferences: 0,
+            first_preferences: vec![0; num_candidates],
+            pairwise: vec![vec![0; num_candidates]; num_candidates],
+        };
+
+        for ballot in ballot_box.ballots() {
+            let count = ballot.count();
+            let mut ranked = vec![false; num_candidates];
+            let mut order = Vec::new();
+            for &id in ballot.preferences() {
+                if ballot_box.is_candidate(id) && !ranked[id] {
+                    ranked[id] = true;
+                    order.push(id);
+                }
+            }
+            if let Some(&first) = order.first() {
+                analysis.first_preferences[first] += count;
+            }
+            analysis.num_preferences += order.len() as u64 * count;
+            // Each candidate is preferred to everyone ranked after them and everyone not ranked.
+            for (position, &id) in order.iter().enumerate() {
+                for &other in &order[position + 1..] {
+                    analysis.pairwise[id][other] += count;
+                }
+                for other in (0..num_candidates).filter(|&other| !ranked[other]) {
+                    analysis.pairwise[id][other] += count;
+                }
+            }
+        }
+        analysis
+    }
+
+    /// The running candidates, in the order they were listed. Indices into the other statistics
+    /// refer to this list.
+    pub fn candidates(&self) -> &[Candidate] {
+        &self.candidates
+    }
+
+    /// The number of ballots cast.
+    pub fn num_ballots(&self) -> u64 {
+        self.num_ballots
+    }
+
+    /// The mean number of candidates ranked on each ballot.
+    pub fn mean_preferences(&self) -> f64 {
+        if self.num_ballots == 0 {
+            0.0
+        } else {
+            self.num_preferences as f64 / self.num_ballots as f64
+        }
+    }
+
+    /// The number of first preferences for each candidate.
+    pub fn first_preferences(&self) -> &[u64] {
+        &self.first_preferences
+    }
+
+    /// The number of ballots which prefer candidate `a` to candidate `b`, by ranking `a` above
+    /// `b` or ranking `a` and not `b`.
+    pub fn pairwise(&self, a: usize, b: usize) -> u64 {
+        self.pairwise[a][b]
+    }
+
+    /// The candidate preferred to every other candidate by more ballots than prefer the other,
+    /// if there is one.
+    pub fn condorcet_winner(&self) -> Option<&str> {
+        (0..self.candidates.len())
+            .find(|&a| {
+                (0..self.candidates.len())
+                    .all(|b| a == b || self.pairwise[a][b] > self.pairwise[b][a])
+            })
+            .map(|a| self.candidates[a].as_str())
+    }
+
+    /// Write the statistics as plain text, with first preferences in a list and the pairwise
+    /// comparisons as a matrix where each row counts the ballots preferring that candidate to the
+    /// candidate in each column.
+    pub fn write_text<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        let name_width = self
+            .candidates
+            .iter()
+            .map(|name| name.chars().count())
+            .max()
+            .unwrap_or(0);
+        let number_width = self.num_ballots.to_string().len();
+
+        writeln!(writer, "Ballots: {}", self.num_ballots)?;
+        writeln!(writer, "Mean preferences: {:.2}", self.mean_preferences())?;
+        writeln!(writer, "\nFirst preferences:")?;
+        for (name, votes) in self.candidates.iter().zip(&self.first_preferences) {
+            writeln!(
+                writer,
+                "  {:<name_width$}  {:>number_width$}",
+                name,
+                votes,
+                name_width = name_width,
+                number_width = number_width
+            )?;
+        }
+
+        writeln!(writer, "\nPairwise preferences (row over column):")?;
+        let widths: Vec<usize> = self
+            .candidates
+            .iter()
+            .map(|name| name.chars().count().max(number_width))
+            .collect();
+        write!(writer, "  {:name_width$}", "", name_width = name_width)?;
+        for (name, width) in self.candidates.iter().zip(&widths) {
+            write!(writer, "  {:>width$}", name, width = width)?;
+        }
+        writeln!(writer)?;
+        for (a, name) in self.candidates.iter().enumerate() {
+            write!(writer, "  {:<name_width$}", name, name_width = name_width)?;
+            for (b, width) in widths.iter().enumerate() {
+                let cell = if a == b {
+                    "-".to_owned()
+                } else {
+                    self.pairwise[a][b].to_string()
+                };
+                write!(writer, "  {:>width$}", cell, width = width)?;
+            }
+            writeln!(writer)?;
+        }
+
+        writeln!(
+            writer,
+            "\nCondorcet winner: {}",
+            self.condorcet_winner().unwrap_or("none")
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const TEST_CSV: &str = "a,b,c,d\na,b\na,b\na,c\na\nb,c\nc\nc\nd,c\na,a,z\n";
+
+    #[test]
+    fn test_analysis() {
+        let ballot_box = BallotBox::from_reader(Cursor::new(TEST_CSV)).unwrap();
+        let analysis = Analysis::new(&ballot_box);
+
+        assert_eq!(9, analysis.num_ballots());
+        assert!((analysis.mean_preferences() - 14.0 / 9.0).abs() < 1e-9);
+        assert_eq!(&[5, 1, 2, 1], analysis.first_preferences());
+        assert_eq!(5, analysis.pairwise(0, 1));
+        assert_eq!(1, analysis.pairwise(1, 0));
+        assert_eq!(4, analysis.pairwise(2, 0));
+        assert_eq!(Some("a"), analysis.condorcet_winner());
+    }
+
+    #[test]
+    fn test_write_text() {
+        let ballot_box = BallotBox::from_reader(Cursor::new("a,b,c\na,b\nb,c\nc,a\n")).unwrap();
+        let mut text = Vec::new();
+        Analysis::new(&ballot_box).write_text(&mut text).unwrap();
+
+        assert_eq!(
+            "Ballots: 3
+Mean preferences: 2.00
+
+First preferences:
+  a  1
+  b  1
+  c  1
+
+Pairwise preferences (row over column):
+     a  b  c
+  a  -  2  1
+  b  1  -  2
+  c  2  1  -
+
+Condorcet winner: none
+",
+            String::from_utf8(text).unwrap()
+        );
+    }
+}
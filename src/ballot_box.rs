@@ -0,0 +1,549 @@
+/* good_stv - a good single transferable vote utility.
+ * Copyright (C) 2019 good_stv authors
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>. */
+
+//! The candidates and ballots of an election, as they were cast.
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::mem;
+use std::path::Path;
+
+use csv::StringRecord;
+use failure::*;
+
+use crate::{Candidate, CandidateInfo, CandidateList, CsvDialect, ElectionError, Vote};
+
+/// Index of a name in [`BallotBox::names`].
+///
+/// The candidates come first, in the order they were listed, followed by any names which appear
+/// on ballots but aren't running.
+pub type CandidateId = usize;
+
+/// A group of identical ballots.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ballot {
+    preferences: Vec<CandidateId>,
+    count: u64,
+}
+
+impl Ballot {
+    /// The names on the ballot, ordered from first preference to last preference.
+    pub fn preferences(&self) -> &[CandidateId] {
+        &self.preferences
+    }
+
+    /// The number of times this ballot was cast.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+}
+
+/// The candidates running in an election and the ballots cast for them.
+///
+/// Names are stored once and referred to by [`CandidateId`], and identical ballots are stored
+/// once along with the number of times they were cast, so that large elections stay compact.
+///
+/// A `BallotBox` never changes once it has been filled, so the same ballots can be counted any
+/// number of times under different rules by a [`Counter`](crate::Counter).
+#[derive(Clone, Debug, PartialEq)]
+pub struct BallotBox {
+    num_candidates: usize,
+    names: Vec<String>,
+    ballots: Vec<Ballot>,
+    title: Option<String>,
+    seats: Option<u64>,
+    withdrawn: Vec<Candidate>,
+    candidate_list: Option<CandidateList>,
+}
+
+impl BallotBox {
+    /// Manually construct a `BallotBox` where the input data is already in memory.
+    ///
+    /// Returns an [`ElectionError`] if a candidate is listed more than once.
+    pub fn new(candidates: Vec<Candidate>, votes: Vec<Vote>) -> Result<Self, Error> {
+        let mut builder = BallotBoxBuilder::new(candidates)?;
+        for vote in votes {
+            builder.push(vote);
+        }
+        Ok(builder.build())
+    }
+
+    /// Construct a `BallotBox` given a path to a CSV file.
+    pub fn from_csv_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        BallotBox::from_csv_file_with(path, &CsvDialect::default())
+    }
+
+    /// Construct a `BallotBox` given a path to a CSV file in the given dialect.
+    pub fn from_csv_file_with<P: AsRef<Path>>(
+        path: P,
+        dialect: &CsvDialect,
+    ) -> Result<Self, Error> {
+        let file = File::open(&path)
+            .context(format!("Error opening file {:?}", path.as_ref().display()))?;
+        BallotBox::from_reader_with(file, dialect)
+    }
+
+    /// Construct a `BallotBox` given any implementation of [`std::io::Read`] which contains CSV
+    /// data.
+    ///
+    /// Ballots are added to the `BallotBox` as they are read, so memory use grows with the number
+    /// of distinct ballots rather than the number of rows.
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self, Error> {
+        BallotBox::from_reader_with(reader, &CsvDialect::default())
+    }
+
+    /// Construct a `BallotBox` given any implementation of [`std::io::Read`] which contains CSV
+    /// data in the given dialect.
+    pub fn from_reader_with<R: Read>(reader: R, dialect: &CsvDialect) -> Result<Self, Error> {
+        let mut csv_reader = dialect.reader(reader, true)?;
+        let mut candidates: Vec<Candidate> = csv_reader
+            .headers()
+            .context("Error parsing CSV header.")?
+            .deserialize(None)
+            .context("Error deserializing CSV into Candidates struct.")?;
+        if dialect.is_trimmed() {
+            candidates.retain(|candidate| !candidate.is_empty());
+        }
+        let builder = BallotBoxBuilder::new(candidates)?;
+        BallotBox::read_csv_ballots(csv_reader, builder, dialect)
+    }
+
+    /// Construct a `BallotBox` given a path to a CSV file which contains only ballots, with the
+    /// candidates given separately.
+    pub fn from_csv_file_with_candidates<P: AsRef<Path>>(
+        path: P,
+        candidate_list: CandidateList,
+        dialect: &CsvDialect,
+    ) -> Result<Self, Error> {
+        let file = File::open(&path)
+            .context(format!("Error opening file {:?}", path.as_ref().display()))?;
+        BallotBox::from_reader_with_candidates(file, candidate_list, dialect)
+    }
+
+    /// Construct a `BallotBox` given any implementation of [`std::io::Read`] which contains CSV
+    /// ballots without a header line, with the candidates given separately.
+    ///
+    /// Withdrawn candidates are removed from the ballots.
+    pub fn from_reader_with_candidates<R: Read>(
+        reader: R,
+        candidate_list: CandidateList,
+        dialect: &CsvDialect,
+    ) -> Result<Self, Error> {
+        let csv_reader = dialect.reader(reader, false)?;
+        let builder = BallotBoxBuilder::from_candidate_list(candidate_list)?;
+        BallotBox::read_csv_ballots(csv_reader, builder, dialect)
+    }
+
+    fn read_csv_ballots<R: Read>(
+        mut csv_reader: csv::Reader<R>,
+        mut builder: BallotBoxBuilder,
+        dialect: &CsvDialect,
+    ) -> Result<Self, Error> {
+        dialect.configure(&mut builder)?;
+        let mut record = StringRecord::new();
+        while csv_reader
+            .read_record(&mut record)
+            .context("Could not deserialize record.")?
+        {
+            if dialect.is_trimmed() {
+                builder.push(record.iter().filter(|name| !name.is_empty()));
+            } else {
+                builder.push(&record);
+            }
+        }
+
+        Ok(builder.build())
+    }
+
+    /// The candidates running in the election, in the order they were listed.
+    pub fn candidates(&self) -> &[Candidate] {
+        &self.names[..self.num_candidates]
+    }
+
+    /// Candidates who withdrew before the count. They are not included in
+    /// [`BallotBox::candidates`], and have been removed from every ballot.
+    pub fn withdrawn(&self) -> &[Candidate] {
+        &self.withdrawn
+    }
+
+    /// Details of the candidates, if they were given in a separate [`CandidateList`].
+    pub fn candidate_list(&self) -> Option<&CandidateList> {
+        self.candidate_list.as_ref()
+    }
+
+    /// The name to show for `candidate` in reports, which is their display name from the
+    /// [`CandidateList`] if they have one.
+    pub fn display_name<'s>(&'s self, candidate: &'s str) -> &'s str {
+        self.candidate_list
+            .as_ref()
+            .and_then(|list| list.get(candidate))
+            .map_or(candidate, CandidateInfo::display_name)
+    }
+
+    /// The title of the election, if the input gave one.
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    /// The number of seats to be filled, if the input gave one.
+    ///
+    /// The `Election` constructors count with `config.seats` even when the input gives a number of
+    /// seats. To count with the input's number, put it in the config and use
+    /// [`Election::from_ballot_box`](crate::Election::from_ballot_box).
+    pub fn seats(&self) -> Option<u64> {
+        self.seats
+    }
+
+    /// Every name which appears in the election, indexed by [`CandidateId`].
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Names which appear on ballots but aren't running candidates, in the order they first
+    /// appeared. Ballots naming them will be spoiled or truncated when counted.
+    pub fn unknown_names(&self) -> &[String] {
+        &self.names[self.num_candidates..]
+    }
+
+    /// Returns whether `id` refers to a candidate who is running, rather than a name which only
+    /// appears on ballots.
+    pub fn is_candidate(&self, id: CandidateId) -> bool {
+        id < self.num_candidates
+    }
+
+    /// Every distinct ballot cast, including any which will be spoiled when counted, in the order
+    /// they first appeared.
+    pub fn ballots(&self) -> &[Ballot] {
+        &self.ballots
+    }
+
+    /// The total number of ballots cast.
+    pub fn num_votes(&self) -> u64 {
+        self.ballots.iter().map(Ballot::count).sum()
+    }
+
+    /// Every ballot cast, with candidates referred to by name. Identical ballots are repeated as
+    /// many times as they were cast.
+    pub fn votes(&self) -> impl Iterator<Item = Vote> + '_ {
+        self.ballots.iter().flat_map(move |ballot| {
+            let vote: Vote = ballot
+                .preferences
+                .iter()
+                .map(|&id| self.names[id].clone())
+                .collect();
+            (0..ballot.count).map(move |_| vote.clone())
+        })
+    }
+
+    /// Write the ballots as CSV, with the running candidates on the first line and one ballot on
+    /// each line after it, which can be read back with [`BallotBox::from_reader`].
+    pub fn write_csv<W: Write>(&self, writer: W) -> Result<(), Error> {
+        let mut csv_writer = csv::WriterBuilder::new().flexible(true).from_writer(writer);
+        csv_writer
+            .write_record(self.candidates())
+            .context("Error writing CSV.")?;
+        for vote in self.votes() {
+            csv_writer
+                .write_record(&vote)
+                .context("Error writing CSV.")?;
+        }
+        csv_writer.flush().context("Error writing CSV.")?;
+        Ok(())
+    }
+}
+
+/// Fills a [`BallotBox`] one ballot at a time.
+///
+/// Names are interned and identical ballots grouped as each ballot is pushed, so ballots can be
+/// streamed in from a large file without holding every one of them in memory.
+///
+/// ```
+/// use good_stv::BallotBoxBuilder;
+///
+/// let mut builder = BallotBoxBuilder::new(vec!["a".to_owned(), "b".to_owned()]).unwrap();
+/// builder.push(&["a", "b"]);
+/// builder.push(&["a", "b"]);
+/// builder.push(&["b"]);
+/// let ballot_box = builder.build();
+///
+/// assert_eq!(3, ballot_box.num_votes());
+/// assert_eq!(2, ballot_box.ballots().len());
+/// ```
+#[derive(Debug)]
+pub struct BallotBoxBuilder {
+    ballot_box: BallotBox,
+    ids: HashMap<String, CandidateId>,
+    ignore_case: bool,
+    withdrawn: HashSet<String>,
+    ballot_ids: HashMap<Vec<CandidateId>, usize>,
+    // Reused between pushes so that repeated ballots don't allocate.
+    preferences: Vec<CandidateId>,
+}
+
+impl BallotBoxBuilder {
+    /// Start a `BallotBox` for an election between `candidates`.
+    ///
+    /// Returns an [`ElectionError`] if a candidate is listed more than once.
+    pub fn new(candidates: Vec<Candidate>) -> Result<Self, Error> {
+        let ids = index_names(&candidates, false)?;
+        Ok(BallotBoxBuilder {
+            ballot_box: BallotBox {
+                num_candidates: candidates.len(),
+                names: candidates,
+                ballots: Vec::new(),
+                title: None,
+                seats: None,
+                withdrawn: Vec::new(),
+                candidate_list: None,
+            },
+            ids,
+            ignore_case: false,
+            withdrawn: HashSet::new(),
+            ballot_ids: HashMap::new(),
+            preferences: Vec::new(),
+        })
+    }
+
+    /// Start a `BallotBox` for an election between the running candidates in `candidate_list`.
+    ///
+    /// Returns an [`ElectionError`] if a candidate is listed more than once.
+    pub fn from_candidate_list(candidate_list: CandidateList) -> Result<Self, Error> {
+        let mut builder = BallotBoxBuilder::new(candidate_list.running())?;
+        builder.set_withdrawn(candidate_list.withdrawn());
+        builder.ballot_box.candidate_list = Some(candidate_list);
+        Ok(builder)
+    }
+
+    /// Add a single ballot, given as names ordered from first preference to last preference.
+    pub fn push<I>(&mut self, vote: I)
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        self.push_weighted(vote, 1);
+    }
+
+    /// Add `count` identical ballots, given as names ordered from first preference to last
+    /// preference.
+    pub fn push_weighted<I>(&mut self, vote: I, count: u64)
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        self.preferences.clear();
+        for name in vote {
+            let name = name.as_ref();
+            let key = fold_case(name, self.ignore_case);
+            if self.withdrawn.contains(key.as_ref()) {
+                continue;
+            }
+            let id = match self.ids.get(key.as_ref()) {
+                Some(&id) => id,
+                None => {
+                    let id = self.ballot_box.names.len();
+                    self.ballot_box.names.push(name.to_owned());
+                    self.ids.insert(key.into_owned(), id);
+                    id
+                }
+            };
+            self.preferences.push(id);
+        }
+
+        let ballots = &mut self.ballot_box.ballots;
+        let ballot_id = match self.ballot_ids.get(&self.preferences[..]) {
+            Some(&ballot_id) => ballot_id,
+            None => {
+                ballots.push(Ballot {
+                    preferences: self.preferences.clone(),
+                    count: 0,
+                });
+                self.ballot_ids
+                    .insert(self.preferences.clone(), ballots.len() - 1);
+                ballots.len() - 1
+            }
+        };
+        ballots[ballot_id].count += count;
+    }
+
+    /// Match names on ballots to candidates regardless of case. Names are kept as they were
+    /// first spelled.
+    ///
+    /// Returns an [`ElectionError`] if two candidates' names only differ by case.
+    pub fn set_ignore_case(&mut self, ignore_case: bool) -> Result<(), Error> {
+        self.ids = index_names(&self.ballot_box.names, ignore_case)?;
+        self.ignore_case = ignore_case;
+        let withdrawn = mem::take(&mut self.ballot_box.withdrawn);
+        self.set_withdrawn(withdrawn);
+        Ok(())
+    }
+
+    /// Set the title of the election.
+    pub fn set_title(&mut self, title: String) {
+        self.ballot_box.title = Some(title);
+    }
+
+    /// Set the number of seats the ballots were cast to fill.
+    pub fn set_seats(&mut self, seats: u64) {
+        self.ballot_box.seats = Some(seats);
+    }
+
+    /// Record candidates who withdrew before the count. They are removed from any ballots pushed
+    /// afterwards.
+    pub fn set_withdrawn(&mut self, withdrawn: Vec<Candidate>) {
+        self.withdrawn = withdrawn
+            .iter()
+            .map(|name| fold_case(name, self.ignore_case).into_owned())
+            .collect();
+        self.ballot_box.withdrawn = withdrawn;
+    }
+
+    // Replaces the names of the candidates, for input formats which only name the candidates after
+    // the ballots.
+    pub(crate) fn rename_candidates(&mut self, candidates: Vec<Candidate>) -> Result<(), Error> {
+        let ballot_box = &mut self.ballot_box;
+        assert_eq!(ballot_box.num_candidates, candidates.len());
+        let others = ballot_box.names.split_off(ballot_box.num_candidates);
+        ballot_box.names = candidates;
+        ballot_box.names.extend(others);
+        self.ids = index_names(&ballot_box.names, self.ignore_case)?;
+        Ok(())
+    }
+
+    /// Returns the number of ballots pushed so far.
+    pub fn num_votes(&self) -> u64 {
+        self.ballot_box.num_votes()
+    }
+
+    /// Finish filling the `BallotBox`.
+    pub fn build(self) -> BallotBox {
+        self.ballot_box
+    }
+}
+
+// Maps each name to its id, failing if any two names are the same.
+fn index_names(names: &[String], ignore_case: bool) -> Result<HashMap<String, CandidateId>, Error> {
+    let mut ids = HashMap::new();
+    for (id, name) in names.iter().enumerate() {
+        if ids
+            .insert(fold_case(name, ignore_case).into_owned(), id)
+            .is_some()
+        {
+            return Err(ElectionError::DuplicateCandidate(name.clone()).into());
+        }
+    }
+    Ok(ids)
+}
+
+fn fold_case(name: &str, ignore_case: bool) -> Cow<'_, str> {
+    if ignore_case {
+        Cow::Owned(name.to_lowercase())
+    } else {
+        Cow::Borrowed(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_read_csv() {
+        let test_csv = "cand1,cand2,cand3\ncand1,cand2";
+        let cursor = Cursor::new(test_csv);
+
+        let ballot_box = BallotBox::from_reader(cursor).unwrap();
+
+        assert_eq!(
+            ballot_box.candidates(),
+            &["cand1".to_owned(), "cand2".to_owned(), "cand3".to_owned()]
+        );
+        assert_eq!(
+            ballot_box.votes().collect::<Vec<_>>(),
+            vec![vec!["cand1".to_owned(), "cand2".to_owned()]]
+        );
+    }
+
+    #[test]
+    fn test_write_csv() {
+        let test_csv = "a,b,c\nb,a\nc\nb,a\nz,a\n";
+        let ballot_box = BallotBox::from_reader(Cursor::new(test_csv)).unwrap();
+        let mut csv = Vec::new();
+        ballot_box.write_csv(&mut csv).unwrap();
+
+        assert_eq!(&["z"], ballot_box.unknown_names());
+        assert_eq!("a,b,c\nb,a\nb,a\nc\nz,a\n", String::from_utf8(csv).unwrap());
+    }
+
+    #[test]
+    fn test_identical_ballots_are_grouped() {
+        let test_csv = "a,b\na,b\nb\na,b\nz,a";
+        let cursor = Cursor::new(test_csv);
+
+        let ballot_box = BallotBox::from_reader(cursor).unwrap();
+
+        assert_eq!(&["a", "b", "z"], ballot_box.names());
+        assert!(!ballot_box.is_candidate(2));
+        assert_eq!(
+            &[
+                Ballot {
+                    preferences: vec![0, 1],
+                    count: 2
+                },
+                Ballot {
+                    preferences: vec![1],
+                    count: 1
+                },
+                Ballot {
+                    preferences: vec![2, 0],
+                    count: 1
+                },
+            ],
+            ballot_box.ballots()
+        );
+        assert_eq!(4, ballot_box.num_votes());
+    }
+
+    #[test]
+    fn test_large_file_is_streamed() {
+        let mut test_csv = "a,b,c\n".to_owned();
+        for _ in 0..10_000 {
+            test_csv.push_str("a,b\nc\nb,c,a\n");
+        }
+        let cursor = Cursor::new(test_csv);
+
+        let ballot_box = BallotBox::from_reader(cursor).unwrap();
+
+        assert_eq!(30_000, ballot_box.num_votes());
+        assert_eq!(3, ballot_box.ballots().len());
+        assert_eq!(10_000, ballot_box.ballots()[1].count());
+    }
+
+    #[test]
+    fn test_duplicate_candidate_is_an_error() {
+        let test_csv = "a,b,a\na\nb";
+        let cursor = Cursor::new(test_csv);
+
+        let err = BallotBox::from_reader(cursor).unwrap_err();
+
+        assert_eq!(
+            Some(&ElectionError::DuplicateCandidate("a".to_owned())),
+            err.downcast_ref::<ElectionError>()
+        );
+    }
+}
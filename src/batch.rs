@@ -0,0 +1,105 @@
+/* good_stv - a good single transferable vote utility.
+ * Copyright (C) 2019 good_stv authors
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>. */
+
+//! Counting several elections at once.
+//!
+//! With the `parallel` feature enabled the elections are spread across a thread pool. Each count
+//! has its own random number generator, so seeded counts give the same results either way.
+
+use failure::*;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::{BallotBox, Counter, Election, ElectionConfig, ElectionResults};
+
+/// Count every election in `elections`, returning their results in the same order.
+pub fn count_elections(elections: &[Election]) -> Vec<Result<ElectionResults, Error>> {
+    map(elections, Election::results)
+}
+
+/// Count the same ballots under each of `configs`, returning the results in the same order.
+///
+/// This is useful for seeing how sensitive an election is to its rules, or for repeating a count
+/// which uses random transfers under many seeds.
+pub fn count_under_rules(
+    ballot_box: &BallotBox,
+    configs: &[ElectionConfig],
+) -> Vec<Result<ElectionResults, Error>> {
+    map(configs, |&config| {
+        Counter::new(ballot_box, config)?.results()
+    })
+}
+
+// Applies `f` to each item, on a thread pool if the `parallel` feature is enabled. The results
+// are always in the same order as `items`.
+#[cfg(feature = "parallel")]
+pub(crate) fn map<T, R, F>(items: &[T], f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Send + Sync,
+{
+    items.par_iter().map(f).collect()
+}
+
+#[cfg(not(feature = "parallel"))]
+pub(crate) fn map<T, R, F>(items: &[T], f: F) -> Vec<R>
+where
+    F: Fn(&T) -> R,
+{
+    items.iter().map(f).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Method;
+    use std::io::Cursor;
+    use std::iter;
+
+    #[test]
+    fn test_batch_matches_single_counts() {
+        let test_csv = "a,b,c,d\nc,b,a\nc,b,a\nb,c\na,b\nc,b\nb,a\nc,b,a\nd,a\na,b\nd,c\nb,d";
+        let ballot_box = BallotBox::from_reader(Cursor::new(test_csv)).unwrap();
+        let configs: Vec<_> = (0..20)
+            .map(|seed| ElectionConfig::new(2).seed(seed))
+            .chain(iter::once(
+                ElectionConfig::new(2).method(Method::Gregory).seed(0),
+            ))
+            .collect();
+
+        let results = count_under_rules(&ballot_box, &configs);
+
+        assert_eq!(configs.len(), results.len());
+        for (config, result) in configs.iter().zip(results) {
+            let expected = Counter::new(&ballot_box, *config).unwrap().results();
+            assert_eq!(expected.unwrap(), result.unwrap());
+        }
+    }
+
+    #[test]
+    fn test_count_elections_keeps_errors() {
+        let elections = vec![
+            Election::from_reader(Cursor::new("a,b\na\na\nb"), ElectionConfig::new(1)).unwrap(),
+            Election::from_reader(Cursor::new("a,b\n\n"), ElectionConfig::new(1)).unwrap(),
+        ];
+
+        let results = count_elections(&elections);
+
+        assert_eq!(Some(&2.0), results[0].as_ref().unwrap().elected().get("a"));
+        assert!(results[1].is_err());
+    }
+}
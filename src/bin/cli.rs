@@ -14,79 +14,792 @@
  * You should have received a copy of the GNU General Public License
  * along with this program.  If not, see <https://www.gnu.org/licenses/>. */
 
-use std::io;
+use std::cmp::Reverse;
+use std::env;
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufWriter, IsTerminal, Write};
+use std::path::Path;
 
-use clap::{App, Arg, ArgMatches};
-use failure::{Error, ResultExt};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
 use env_logger::{Builder, Env};
+use failure::{format_err, Error, ResultExt};
 
 use good_stv::*;
 
 const VERSION: Option<&str> = option_env!("CARGO_PKG_VERSION");
 
 fn main() -> Result<(), Error> {
-    Builder::from_env(Env::default().default_filter_or("off")).init();
     let matches = parse_opts();
-    let seats: u64 = matches
-        .value_of("seats")
+    // One -v describes each stage of the count, and a second turns on the library's log messages
+    // as well. RUST_LOG still takes precedence.
+    let log_level = match matches.occurrences_of("verbose") {
+        0 | 1 => "off",
+        _ => "info",
+    };
+    Builder::from_env(Env::default().default_filter_or(log_level)).init();
+    match matches.subcommand() {
+        ("validate", Some(matches)) => validate(matches),
+        ("convert", Some(matches)) => convert(matches),
+        ("analyse", Some(matches)) => analyse(matches),
+        ("simulate", Some(matches)) => simulate(matches),
+        ("tui", Some(matches)) => tui(matches),
+        ("batch", Some(matches)) => batch(matches),
+        (_, Some(matches)) => count(matches),
+        (_, None) => unreachable!("a subcommand is always given"),
+    }
+}
+
+fn count(matches: &ArgMatches) -> Result<(), Error> {
+    let ballot_box = read_ballot_box(matches)?;
+    let config = parse_config(matches, ballot_box.seats())?;
+    let election = Election::from_ballot_box(ballot_box, config)?;
+    if let Some(path) = matches.value_of("export-blt") {
+        let file = File::create(path).context(format!("Error creating file {:?}", path))?;
+        election.write_blt(BufWriter::new(file))?;
+    }
+
+    let counter = election.counter();
+    let results = counter.results()?;
+    if matches.occurrences_of("verbose") > 0 {
+        for round in results.rounds() {
+            counter.write_narrative(round, io::stderr().lock())?;
+        }
+    }
+    let labels = match matches.value_of("labels") {
+        Some(path) => Labels::from_file(path)?,
+        None => Labels::default(),
+    };
+    let sheet = CountSheet::with_labels(&counter, &results, labels);
+
+    if let Some(path) = matches.value_of("count-sheet") {
+        let file = File::create(path).context(format!("Error creating file {:?}", path))?;
+        sheet.write_csv(BufWriter::new(file))?;
+    }
+
+    if let Some(path) = matches.value_of("html") {
+        let file = File::create(path).context(format!("Error creating file {:?}", path))?;
+        counter.write_html(&results, BufWriter::new(file))?;
+    }
+
+    if let Some(path) = matches.value_of("flows") {
+        let file = File::create(path).context(format!("Error creating file {:?}", path))?;
+        results.write_flows_csv(BufWriter::new(file))?;
+    }
+    if let Some(path) = matches.value_of("sankey") {
+        let file = File::create(path).context(format!("Error creating file {:?}", path))?;
+        counter.write_sankey_svg(&results, BufWriter::new(file))?;
+    }
+
+    if let Some(dir) = matches.value_of("bar-charts") {
+        fs::create_dir_all(dir).context(format!("Error creating directory {:?}", dir))?;
+        for round in results.rounds() {
+            let path = Path::new(dir).join(format!("stage-{}.svg", round.number()));
+            let file =
+                File::create(&path).context(format!("Error creating file {:?}", path.display()))?;
+            counter.write_round_svg(round, BufWriter::new(file))?;
+        }
+    }
+
+    match matches.value_of("output") {
+        Some("json") => Report::new(&counter, &results).write_json(io::stdout().lock())?,
+        Some("markdown") => sheet.write_markdown(io::stdout().lock())?,
+        _ => sheet.write_text(io::stdout().lock())?,
+    }
+
+    Ok(())
+}
+
+// Checks that the ballots can be read and counted under the rules, without counting them.
+fn validate(matches: &ArgMatches) -> Result<(), Error> {
+    let ballot_box = read_ballot_box(matches)?;
+    let config = parse_config(matches, ballot_box.seats().or(Some(1)))?;
+    let counter = Counter::new(&ballot_box, config)?;
+    println!("Candidates: {}", ballot_box.candidates().join(", "));
+    if !ballot_box.withdrawn().is_empty() {
+        println!("Withdrawn: {}", ballot_box.withdrawn().join(", "));
+    }
+    if !ballot_box.unknown_names().is_empty() {
+        println!("Unknown names: {}", ballot_box.unknown_names().join(", "));
+    }
+    println!("Ballots: {}", ballot_box.num_votes());
+    println!("Valid ballots: {}", counter.total_votes());
+    println!("Spoiled ballots: {}", counter.num_spoiled_votes());
+    Ok(())
+}
+
+fn convert(matches: &ArgMatches) -> Result<(), Error> {
+    let ballot_box = read_ballot_box(matches)?;
+    let writer: Box<dyn Write> = match matches.value_of("out") {
+        Some(path) => Box::new(BufWriter::new(
+            File::create(path).context(format!("Error creating file {:?}", path))?,
+        )),
+        None => Box::new(io::stdout()),
+    };
+    match matches.value_of("to") {
+        Some("blt") => {
+            let config = parse_config(matches, ballot_box.seats())?;
+            Election::from_ballot_box(ballot_box, config)?.write_blt(writer)
+        }
+        Some("json") => ballot_box.write_json(writer),
+        Some("jsonl") => ballot_box.write_json_lines(writer),
+        _ => ballot_box.write_csv(writer),
+    }
+}
+
+fn analyse(matches: &ArgMatches) -> Result<(), Error> {
+    let ballot_box = read_ballot_box(matches)?;
+    Analysis::new(&ballot_box).write_text(io::stdout().lock())
+}
+
+// Counts the ballots once for each seed, to show how much the result depends on chance. The seeds
+// start from --seed, so a different set of runs can be tried.
+fn simulate(matches: &ArgMatches) -> Result<(), Error> {
+    let ballot_box = read_ballot_box(matches)?;
+    let config = parse_config(matches, ballot_box.seats())?;
+    let runs = matches
+        .value_of("runs")
         .unwrap()
         .parse::<u64>()
-        .context("Invalid input for seats. Must be an integer.")?;
-    let election = if matches.is_present("file") {
-        Election::from_csv_file(matches.value_of("file").unwrap(), seats)?
-    } else {
-        Election::from_reader(io::stdin(), seats)?
-    };
+        .context("Invalid input for runs. Must be an integer.")?;
+    let first_seed = config.seed.unwrap_or(0);
+    let configs: Vec<ElectionConfig> = (0..runs)
+        .map(|run| config.seed(first_seed.wrapping_add(run)))
+        .collect();
 
-    let results = election.results()?;
+    let mut times_elected = vec![0; ballot_box.candidates().len()];
+    let mut failed = 0;
+    for results in count_under_rules(&ballot_box, &configs) {
+        match results {
+            Ok(results) => {
+                for (i, candidate) in ballot_box.candidates().iter().enumerate() {
+                    if results.elected().contains_key(candidate) {
+                        times_elected[i] += 1;
+                    }
+                }
+            }
+            Err(_) => failed += 1,
+        }
+    }
 
-    print_results(&results);
+    let mut candidates: Vec<_> = ballot_box.candidates().iter().zip(times_elected).collect();
+    candidates.sort_by_key(|&(_, count)| Reverse(count));
+    let name_width = candidates
+        .iter()
+        .map(|(name, _)| ballot_box.display_name(name).chars().count())
+        .max()
+        .unwrap_or(0);
+    println!("Elected in {} runs:", runs);
+    for (name, count) in candidates {
+        println!(
+            "  {:<width$}  {:>6.2}%",
+            ballot_box.display_name(name),
+            100.0 * count as f64 / runs.max(1) as f64,
+            width = name_width
+        );
+    }
+    if failed > 0 {
+        println!("{} runs could not be counted.", failed);
+    }
+    Ok(())
+}
+
+const SUBCOMMANDS: [&str; 7] = [
+    "count", "validate", "convert", "analyse", "simulate", "tui", "batch",
+];
+
+// Counts every position listed in a manifest and prints their results together.
+fn batch(matches: &ArgMatches) -> Result<(), Error> {
+    let manifest = Manifest::from_file(matches.value_of("manifest").unwrap())?;
+    let elections = manifest.elections()?;
+    let results = manifest.count(&elections)?;
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
 
+    let markdown = match matches.value_of("output") {
+        Some("json") => {
+            return CombinedReport::new(&manifest, &elections, &results).write_json(stdout);
+        }
+        Some("markdown") => true,
+        _ => false,
+    };
+    let labels = match matches.value_of("labels") {
+        Some(path) => Labels::from_file(path)?,
+        None => Labels::default(),
+    };
+    if let Some(title) = manifest.title() {
+        if markdown {
+            writeln!(stdout, "# {}\n", title)?;
+        } else {
+            writeln!(stdout, "{}\n{}\n", title, "=".repeat(title.chars().count()))?;
+        }
+    }
+    for ((position, election), results) in manifest.positions().iter().zip(&elections).zip(&results)
+    {
+        let counter = election.counter();
+        let sheet = CountSheet::with_labels(&counter, results, labels.clone());
+        if markdown {
+            writeln!(stdout, "## {}\n", position.name())?;
+            sheet.write_markdown(&mut stdout)?;
+        } else {
+            writeln!(
+                stdout,
+                "{}\n{}\n",
+                position.name(),
+                "-".repeat(position.name().chars().count())
+            )?;
+            sheet.write_text(&mut stdout)?;
+        }
+        writeln!(stdout)?;
+    }
     Ok(())
 }
 
+const TUI_HELP: &str = "[Enter] or n: next stage  p: previous stage  <number>: go to stage  \
+                        <name>: show a candidate's pile  q: quit";
+
+// Steps through a finished count a stage at a time at a prompt, reading one command per line.
+fn tui(matches: &ArgMatches) -> Result<(), Error> {
+    let ballot_box = read_ballot_box(matches)?;
+    let config = parse_config(matches, ballot_box.seats())?;
+    let counter = Counter::new(&ballot_box, config)?;
+    let results = counter.results()?;
+    let mut browser = CountBrowser::new(&counter, &results);
+    let mut pile = None;
+    let mut message = String::new();
+    let stdin = io::stdin();
+    let mut line = String::new();
+
+    loop {
+        let stdout = io::stdout();
+        let mut stdout = stdout.lock();
+        if stdout.is_terminal() {
+            // Clear the screen and move to the top left corner.
+            write!(stdout, "\x1b[2J\x1b[H")?;
+        }
+        browser.write_stage(&mut stdout)?;
+        if let Some(candidate) = pile {
+            writeln!(stdout)?;
+            browser.write_pile(candidate, &mut stdout)?;
+        }
+        writeln!(stdout)?;
+        if !message.is_empty() {
+            writeln!(stdout, "{}", message)?;
+            message.clear();
+        }
+        write!(stdout, "{}\n> ", TUI_HELP)?;
+        stdout.flush()?;
+
+        line.clear();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        match line.trim() {
+            "q" | "quit" => return Ok(()),
+            "" | "n" | "next" => {
+                if !browser.forward() {
+                    message = "This is the last stage.".to_owned();
+                }
+            }
+            "p" | "previous" => {
+                if !browser.back() {
+                    message = "This is the first stage.".to_owned();
+                }
+            }
+            command => match command.parse::<u64>() {
+                Ok(stage) => {
+                    if !browser.go_to(stage) {
+                        message = format!("There is no stage {}.", stage);
+                    }
+                }
+                Err(_) => match browser.find_candidate(command) {
+                    Some(candidate) => pile = Some(candidate),
+                    None => message = format!("There is no candidate called {:?}.", command),
+                },
+            },
+        }
+    }
+}
+
 fn parse_opts<'a>() -> ArgMatches<'a> {
+    // `count` is the default, so that `good_stv 2 -f votes.csv` keeps working.
+    let mut args: Vec<OsString> = env::args_os().collect();
+    let explicit = args
+        .iter()
+        .skip(1)
+        .filter_map(|arg| arg.to_str())
+        .find(|&arg| {
+            arg != "--verbose" && !(arg.starts_with("-v") && arg[1..].bytes().all(|c| c == b'v'))
+        })
+        .is_some_and(|arg| {
+            SUBCOMMANDS.contains(&arg) || ["help", "-h", "--help", "-V", "--version"].contains(&arg)
+        });
+    if !explicit {
+        args.insert(1, OsString::from("count"));
+    }
+
     App::new("good_stv")
         .version(VERSION.unwrap_or("unknown"))
         .author("Terry Bolt <tbolt@redbrick.dcu.ie>")
         .about("A tool for evaluating elections using Single Transferable Vote.")
-        .arg(
-            Arg::with_name("seats")
-                .help("Number of seats to be filled.")
-                .index(1)
-                .required(true),
-        )
+        .after_help("If no subcommand is given, count is used.")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .setting(AppSettings::VersionlessSubcommands)
         .arg(
             Arg::with_name("verbose")
-                .help("Whether to print logging information.")
+                .help("Describe each stage of the count. Give twice to also print log messages.")
+                .long_help(
+                    "Describe each stage of the count on standard error: who was elected or \
+                     excluded, the size of any surplus, and where the votes went. Give twice \
+                     (-vv) to also print log messages, such as spoiled ballots and random \
+                     tie-breaks.",
+                )
                 .long("verbose")
-                .short("v"),
+                .short("v")
+                .multiple(true)
+                .global(true),
         )
-        .arg(
-            Arg::with_name("file")
-                .short("f")
-                .long("file")
-                .value_name("FILE")
-                .help("CSV file to read votes from.")
-                .long_help(
-                    "The CSV file must be in the following format:
+        .subcommand(
+            SubCommand::with_name("count")
+                .about("Count the ballots and print the results.")
+                .args(&rules_args())
+                .args(&input_args())
+                .args(&report_args())
+                .args(&output_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("validate")
+                .about("Check that the ballots can be read and counted, without counting them.")
+                .long_about(
+                    "Check that the ballots can be read and counted under the rules, without \
+                     counting them. Prints the number of valid and spoiled ballots, and any \
+                     names on ballots which aren't running.",
+                )
+                .args(&rules_args())
+                .args(&input_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("convert")
+                .about("Write the ballots in another format.")
+                .args(&rules_args())
+                .args(&input_args())
+                .arg(
+                    Arg::with_name("to")
+                        .long("to")
+                        .value_name("FORMAT")
+                        .possible_values(&["csv", "blt", "json", "jsonl"])
+                        .required(true)
+                        .help("Format to write the ballots in. BLT needs the number of seats."),
+                )
+                .arg(
+                    Arg::with_name("out")
+                        .long("out")
+                        .value_name("FILE")
+                        .help("File to write the ballots to, instead of standard output."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("analyse")
+                .about("Print statistics about the ballots, including a pairwise matrix.")
+                .long_about(
+                    "Print statistics about the ballots without counting them: the number of \
+                     ballots, how many preferences they hold, first preferences, how often each \
+                     candidate is preferred to each other candidate, and the Condorcet winner if \
+                     there is one.",
+                )
+                .args(&rules_args())
+                .args(&input_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("simulate")
+                .about(
+                    "Count the ballots under many seeds and print how often each candidate wins.",
+                )
+                .long_about(
+                    "Count the ballots once for each of --runs seeds, starting from --seed or 0, \
+                     and print how often each candidate was elected. Only counts using random \
+                     transfers or random tie-breaks can differ between seeds.",
+                )
+                .args(&rules_args())
+                .args(&input_args())
+                .arg(
+                    Arg::with_name("runs")
+                        .long("runs")
+                        .value_name("N")
+                        .default_value("100")
+                        .help("Number of times to count the ballots."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("tui")
+                .about("Step forwards and backwards through the count at an interactive prompt.")
+                .long_about(
+                    "Count the ballots, then step forwards and backwards through the stages of \
+                     the count at a prompt, typing one command per line. Each stage shows every \
+                     candidate's votes, who was elected or excluded and where their votes went. \
+                     Type a candidate's name to see where the votes on their pile came from.",
+                )
+                .args(&rules_args())
+                .args(&input_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("batch")
+                .about("Count every position listed in a manifest and print their results.")
+                .long_about(
+                    "Count every position listed in a TOML or JSON manifest, each with its own \
+                     ballots, seats and rules, and print their results together. See the README \
+                     for the layout of the manifest.",
+                )
+                .arg(
+                    Arg::with_name("manifest")
+                        .value_name("MANIFEST")
+                        .required(true)
+                        .help("TOML or JSON file listing each position's ballots, seats and rules.")
+                        .index(1),
+                )
+                .args(&report_args()),
+        )
+        .get_matches_from(args)
+}
+
+// Options for the rules of the election, shared by every subcommand.
+fn rules_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("seats")
+            .help("Number of seats to be filled.")
+            .long_help(
+                "Number of seats to be filled. May be left out if it is given in the config \
+                 file or the BLT file.",
+            )
+            .index(1),
+        Arg::with_name("config")
+            .short("c")
+            .long("config")
+            .value_name("FILE")
+            .help("TOML or JSON file containing the rules of the election.")
+            .long_help(
+                "TOML or JSON file containing the rules of the election. Options given on \
+                 the command line override those in the file.",
+            ),
+        Arg::with_name("method")
+            .long("method")
+            .value_name("METHOD")
+            .possible_values(&["random", "gregory"])
+            .help("How surplus votes are transferred."),
+        Arg::with_name("quota")
+            .long("quota")
+            .value_name("QUOTA")
+            .possible_values(&["droop", "hare"])
+            .help("Formula for the number of votes needed to be elected."),
+        Arg::with_name("tie-break")
+            .long("tie-break")
+            .value_name("RULE")
+            .possible_values(&["random", "forwards", "backwards"])
+            .help("How ties for exclusion are broken."),
+        Arg::with_name("seed")
+            .long("seed")
+            .value_name("SEED")
+            .help("Seed for the random number generator, to make the count repeatable."),
+        Arg::with_name("ballot-policy")
+            .long("ballot-policy")
+            .value_name("POLICY")
+            .possible_values(&["spoil", "truncate", "strict"])
+            .help("What to do with empty ballots or votes for unknown candidates."),
+        Arg::with_name("precision")
+            .long("precision")
+            .value_name("PLACES")
+            .help("Number of decimal places kept when transferring fractional votes."),
+    ]
+}
+
+// Options for reading the ballots, shared by every subcommand.
+fn input_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("format")
+            .long("format")
+            .value_name("FORMAT")
+            .possible_values(&["csv", "blt", "json", "jsonl"])
+            .help("Format of the ballots. Guessed from the file extension if not given.")
+            .long_help(
+                "Format of the ballots. If not given, files ending in .blt are read as BLT, \
+                 .json as JSON, .jsonl or .ndjson as JSON Lines, and anything else, \
+                 including standard input, as CSV.",
+            ),
+        Arg::with_name("ranks")
+            .long("ranks")
+            .help("Read a CSV file with one column per candidate, holding each voter's ranks.")
+            .long_help(
+                "Read a CSV file with one column per candidate, as exported by survey tools \
+                 such as Google Forms. Each row holds the rank the voter gave each candidate, \
+                 with unranked candidates left blank. Ballots with tied or invalid ranks are \
+                 handled according to the ballot policy.",
+            ),
+        Arg::with_name("candidates")
+            .long("candidates")
+            .value_name("FILE")
+            .conflicts_with("ranks")
+            .help("CSV, JSON or TOML file listing the candidates, for ballots without a header.")
+            .long_help(
+                "CSV, JSON or TOML file listing the candidates, so that the ballots need not \
+                 start with a header line. The list may also give each candidate a \
+                 display_name, party and category, and mark them as withdrawn.",
+            ),
+        Arg::with_name("delimiter")
+            .long("delimiter")
+            .value_name("CHAR")
+            .help("Character separating fields in CSV files. Defaults to ','. Use 'tab' for tabs."),
+        Arg::with_name("quote")
+            .long("quote")
+            .value_name("CHAR")
+            .help("Character used to quote fields in CSV files. Defaults to '\"'."),
+        Arg::with_name("no-quoting")
+            .long("no-quoting")
+            .conflicts_with("quote")
+            .help("Read quotes in CSV files as part of the field."),
+        Arg::with_name("comment")
+            .long("comment")
+            .value_name("CHAR")
+            .help("Skip lines in CSV files starting with this character."),
+        Arg::with_name("trim")
+            .long("trim")
+            .help("Trim whitespace around names in CSV files, and skip empty cells."),
+        Arg::with_name("ignore-case")
+            .long("ignore-case")
+            .help("Match names on ballots to candidates regardless of case."),
+        Arg::with_name("no-bom")
+            .long("no-bom")
+            .help("Don't decode CSV files starting with a UTF-16 byte order mark as UTF-16."),
+        Arg::with_name("file")
+            .short("f")
+            .long("file")
+            .value_name("FILE")
+            .help("File to read votes from, instead of standard input.")
+            .long_help(
+                "File to read votes from, instead of standard input. See --format for the \
+                 formats which can be read. CSV files must be in the following format:
 
 candidate_name,candidate_name,candidate_name,...
 first_preference_candidate,second_preference_candidate,...
 first_preference_candidate,second_preference_candidate,...
 ...",
-                ),
-        )
-        .get_matches()
+            ),
+    ]
 }
 
-fn print_results(results: &ElectionResults) {
-    println!("Elected:");
-    for elected in results.elected() {
-        println!("\t{} with {} votes.", elected.0, elected.1);
+// Options for the results of a count.
+fn output_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("export-blt")
+            .long("export-blt")
+            .value_name("FILE")
+            .help("Also write the ballots to a BLT file, to check the count with other tools."),
+        Arg::with_name("count-sheet")
+            .long("count-sheet")
+            .value_name("FILE")
+            .help("Also write a count sheet, with a column for each stage, as CSV.")
+            .long_help(
+                "Also write a count sheet as CSV, with a row per candidate and columns for \
+                 the transfers and totals at each stage, followed by rows for \
+                 non-transferable votes and totals. It can be opened in any spreadsheet \
+                 program.",
+            ),
+        Arg::with_name("html")
+            .long("html")
+            .value_name("FILE")
+            .help("Also write the results as a self-contained HTML page."),
+        Arg::with_name("flows")
+            .long("flows")
+            .value_name("FILE")
+            .help("Also write every transfer as CSV, for charting where the votes went.")
+            .long_help(
+                "Also write every transfer as CSV, with the columns stage, source, \
+                 destination and votes. Exhausted votes have an empty destination.",
+            ),
+        Arg::with_name("sankey")
+            .long("sankey")
+            .value_name("FILE")
+            .help("Also draw where the votes went at each stage as an SVG Sankey diagram."),
+        Arg::with_name("bar-charts")
+            .long("bar-charts")
+            .value_name("DIR")
+            .help("Also draw a bar chart of the tallies at each stage, as SVG files in DIR.")
+            .long_help(
+                "Also draw a bar chart of each continuing candidate's tally against the \
+                 quota at each stage, saved in DIR as stage-1.svg, stage-2.svg and so on.",
+            ),
+    ]
+}
+
+fn read_ballot_box(matches: &ArgMatches) -> Result<BallotBox, Error> {
+    let file = matches.value_of("file");
+    let format = match (matches.value_of("format"), file) {
+        (Some(format), _) => format,
+        (None, Some(path)) => detect_format(path),
+        (None, None) => "csv",
+    };
+    if matches.is_present("ranks") && format != "csv" {
+        return Err(format_err!("--ranks can only be used with CSV files."));
     }
-    println!("\nEliminated:");
-    for eliminated in results.eliminated() {
-        println!("\t{} with {} votes.", eliminated.0, eliminated.1);
+    if matches.is_present("candidates") && format != "csv" {
+        return Err(format_err!("--candidates can only be used with CSV files."));
     }
+    match format {
+        "blt" => match file {
+            Some(path) => BallotBox::from_blt_file(path),
+            None => BallotBox::from_blt_reader(io::stdin()),
+        },
+        "json" => match file {
+            Some(path) => BallotBox::from_json_file(path),
+            None => BallotBox::from_json_reader(io::stdin()),
+        },
+        "jsonl" => match file {
+            Some(path) => BallotBox::from_json_lines_file(path),
+            None => BallotBox::from_json_lines_reader(io::stdin()),
+        },
+        _ if matches.is_present("ranks") => {
+            // How ties are handled depends on the ballot policy, so the rules are needed up front.
+            let ballot_policy = parse_config(matches, None)?.ballot_policy;
+            let dialect = parse_dialect(matches)?;
+            match file {
+                Some(path) => BallotBox::from_rank_csv_file_with(path, ballot_policy, &dialect),
+                None => BallotBox::from_rank_reader_with(io::stdin(), ballot_policy, &dialect),
+            }
+        }
+        _ if matches.is_present("candidates") => {
+            let candidate_list = CandidateList::from_file(matches.value_of("candidates").unwrap())?;
+            let dialect = parse_dialect(matches)?;
+            match file {
+                Some(path) => {
+                    BallotBox::from_csv_file_with_candidates(path, candidate_list, &dialect)
+                }
+                None => {
+                    BallotBox::from_reader_with_candidates(io::stdin(), candidate_list, &dialect)
+                }
+            }
+        }
+        _ => {
+            let dialect = parse_dialect(matches)?;
+            match file {
+                Some(path) => BallotBox::from_csv_file_with(path, &dialect),
+                None => BallotBox::from_reader_with(io::stdin(), &dialect),
+            }
+        }
+    }
+}
+
+fn parse_dialect(matches: &ArgMatches) -> Result<CsvDialect, Error> {
+    let mut dialect = CsvDialect::new()
+        .trim(matches.is_present("trim"))
+        .ignore_case(matches.is_present("ignore-case"))
+        .bom(!matches.is_present("no-bom"));
+    if let Some(delimiter) = matches.value_of("delimiter") {
+        dialect = dialect.delimiter(parse_char("delimiter", delimiter)?);
+    }
+    if matches.is_present("no-quoting") {
+        dialect = dialect.quote(None);
+    } else if let Some(quote) = matches.value_of("quote") {
+        dialect = dialect.quote(Some(parse_char("quote", quote)?));
+    }
+    if let Some(comment) = matches.value_of("comment") {
+        dialect = dialect.comment(Some(parse_char("comment", comment)?));
+    }
+    Ok(dialect)
+}
+
+// Parses a single ASCII character given on the command line, allowing tabs to be written as `\t`
+// or `tab`.
+fn parse_char(name: &str, value: &str) -> Result<u8, Error> {
+    match value {
+        "\\t" | "tab" => Ok(b'\t'),
+        _ if value.len() == 1 && value.is_ascii() => Ok(value.as_bytes()[0]),
+        _ => Err(format_err!(
+            "Invalid input for {}. Must be a single character.",
+            name
+        )),
+    }
+}
+
+// Guesses the format of a file from its extension, defaulting to CSV.
+fn detect_format(path: &str) -> &'static str {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_lowercase);
+    match extension.as_deref() {
+        Some("blt") => "blt",
+        Some("json") => "json",
+        Some("jsonl") | Some("ndjson") => "jsonl",
+        _ => "csv",
+    }
+}
+
+// `default_seats` is the number of seats given alongside the ballots, if any. It is overridden by
+// the config file, which is overridden by the command line. A config file may leave seats out.
+fn parse_config(matches: &ArgMatches, default_seats: Option<u64>) -> Result<ElectionConfig, Error> {
+    let seats = match matches.value_of("seats") {
+        Some(seats) => Some(
+            seats
+                .parse::<u64>()
+                .context("Invalid input for seats. Must be an integer.")?,
+        ),
+        None => None,
+    };
+    let mut config = match matches.value_of("config") {
+        Some(path) => ElectionConfig::from_file_with_seats(path, seats.or(default_seats))?,
+        None => ElectionConfig::new(seats.or(default_seats).ok_or_else(|| {
+            format_err!("The number of seats must be given, unless it is in the config file.")
+        })?),
+    };
+    if let Some(seats) = seats {
+        config = config.seats(seats);
+    }
+    if let Some(method) = matches.value_of("method") {
+        config = config.method(method.parse()?);
+    }
+    if let Some(quota) = matches.value_of("quota") {
+        config = config.quota(quota.parse()?);
+    }
+    if let Some(tie_break) = matches.value_of("tie-break") {
+        config = config.tie_break(tie_break.parse()?);
+    }
+    if let Some(seed) = matches.value_of("seed") {
+        config = config.seed(
+            seed.parse::<u64>()
+                .context("Invalid input for seed. Must be an integer.")?,
+        );
+    }
+    if let Some(ballot_policy) = matches.value_of("ballot-policy") {
+        config = config.ballot_policy(ballot_policy.parse()?);
+    }
+    if let Some(precision) = matches.value_of("precision") {
+        config = config.precision(
+            precision
+                .parse::<u32>()
+                .context("Invalid input for precision. Must be an integer.")?,
+        );
+    }
+    Ok(config)
+}
+
+// Options for how results are printed, shared by `count` and `batch`.
+fn report_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("output")
+            .short("o")
+            .long("output")
+            .value_name("FORMAT")
+            .possible_values(&["text", "markdown", "json"])
+            .default_value("text")
+            .help("Format of the results.")
+            .long_help(
+                "Format of the results. text and markdown give who was elected and a table \
+                 of the votes at every stage. json gives who was elected and excluded in \
+                 order, the tallies at every stage, the quota, the number of spoiled \
+                 ballots and the rules used. See the README for the layout.",
+            ),
+        Arg::with_name("labels")
+            .long("labels")
+            .value_name("FILE")
+            .help("TOML or JSON file of headings to use in tables, to translate them."),
+    ]
 }
@@ -53,7 +53,15 @@ fn rocket() -> rocket::Rocket {
         })
         .mount(
             "/",
-            routes![get_poll, close_poll, create_poll, vote, get_results],
+            routes![
+                get_poll,
+                close_poll,
+                create_poll,
+                vote,
+                get_results,
+                get_blt,
+                get_report
+            ],
         )
         .register(catchers![not_found, internal_error])
 }
@@ -17,12 +17,14 @@
 use std::iter;
 
 use chrono::{DateTime, Local};
+use failure::Error;
 use rand::Rng;
 use serde_derive::*;
 
-use good_stv::Vote;
+use good_stv::{BallotBoxBuilder, Election, ElectionConfig, Vote};
 
 const ID_LENGTH: usize = 6;
+const ADMIN_KEY_LENGTH: usize = 8;
 
 #[derive(Debug, Deserialize, PartialEq, Serialize)]
 pub struct Candidate {
@@ -43,6 +45,13 @@ impl Candidate {
 pub struct PollCreationRequest {
     pub name: String,
     pub candidates: Vec<String>,
+    #[serde(default = "default_seats")]
+    pub seats: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PollCloseRequest {
+    pub admin_key: String,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -52,14 +61,18 @@ pub struct Poll {
     pub created_at: DateTime<Local>,
     pub in_progress: bool,
     pub candidates: Vec<Candidate>,
+    #[serde(default = "default_seats")]
+    pub seats: u64,
     #[serde(skip)] // We don't want votes to be returned by GET /polls/<id>
     votes: Vec<Vote>,
+    #[serde(skip)]
+    admin_key: String,
 }
 
 impl Poll {
     pub fn new(name: String, candidates: Vec<String>) -> Self {
         Poll {
-            id: Poll::generate_id(),
+            id: Poll::generate_id(ID_LENGTH),
             name,
             created_at: Local::now(),
             in_progress: true,
@@ -67,22 +80,58 @@ impl Poll {
                 .iter()
                 .map(|candidate| Candidate::new(candidate.to_string()))
                 .collect(),
+            seats: default_seats(),
             votes: Vec::new(),
+            admin_key: Poll::generate_id(ADMIN_KEY_LENGTH),
         }
     }
 
+    pub fn admin_key(&self) -> &str {
+        &self.admin_key
+    }
+
     pub fn add_vote(&mut self, vote: Vote) {
         self.votes.push(vote);
     }
 
-    fn generate_id() -> String {
+    pub fn close(&mut self) {
+        self.in_progress = false;
+    }
+
+    /// The votes cast so far, as an election between the candidates' names. Votes refer to
+    /// candidates by id, and any id which isn't a candidate is left as it is.
+    pub fn election(&self) -> Result<Election, Error> {
+        let names = self
+            .candidates
+            .iter()
+            .map(|candidate| candidate.name.clone())
+            .collect();
+        let mut builder = BallotBoxBuilder::new(names)?;
+        for vote in &self.votes {
+            builder.push(vote.iter().map(|id| {
+                self.candidates
+                    .iter()
+                    .find(|candidate| &candidate.id == id)
+                    .map_or(id, |candidate| &candidate.name)
+            }));
+        }
+        builder.set_title(self.name.clone());
+        builder.set_seats(self.seats);
+        Election::from_ballot_box(builder.build(), ElectionConfig::new(self.seats))
+    }
+
+    fn generate_id(length: usize) -> String {
         iter::repeat(())
             .map(|()| rand::thread_rng().sample(rand::distributions::Alphanumeric))
-            .take(ID_LENGTH)
+            .take(length)
             .collect()
     }
 }
 
+fn default_seats() -> u64 {
+    1
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -115,4 +164,23 @@ mod tests {
         assert_eq!(true, poll.in_progress);
         assert_eq!(expected_candidates, poll.candidates);
     }
+
+    #[test]
+    fn poll_election() {
+        let mut poll = Poll::new(
+            "test".to_string(),
+            vec!["Alice".to_string(), "Bob Smith".to_string()],
+        );
+        poll.add_vote(vec!["bob_smith".to_string(), "alice".to_string()]);
+        poll.add_vote(vec!["alice".to_string()]);
+        poll.add_vote(vec!["bob_smith".to_string()]);
+
+        let mut blt = Vec::new();
+        poll.election().unwrap().write_blt(&mut blt).unwrap();
+
+        assert_eq!(
+            "2 1\n1 2 1 0\n1 1 0\n1 2 0\n0\n\"Alice\"\n\"Bob Smith\"\n\"test\"\n",
+            String::from_utf8(blt).unwrap()
+        );
+    }
 }
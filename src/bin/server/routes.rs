@@ -14,10 +14,7 @@
  * You should have received a copy of the GNU General Public License
  * along with this program.  If not, see <https://www.gnu.org/licenses/>. */
 
-use std::iter;
-
 use log::info;
-use rand::Rng;
 use rocket::http::Status;
 use rocket::http::{Cookie, Cookies};
 use rocket::response::content;
@@ -26,26 +23,22 @@ use rocket::*;
 use rocket_contrib::json::Json;
 use serde_json::json;
 
-use crate::poll::{Poll, PollCreationRequest};
+use crate::poll::{Poll, PollCloseRequest, PollCreationRequest};
 use crate::PollDb;
 
-const ADMIN_KEY_LENGTH: usize = 8;
-
 #[post("/polls", data = "<poll_req>")]
 pub fn create_poll(
     poll_req: Json<PollCreationRequest>,
     poll_db: State<PollDb>,
 ) -> Created<content::Json<String>> {
-    let poll = Poll::new(poll_req.name.clone(), poll_req.candidates.clone());
+    let mut poll = Poll::new(poll_req.name.clone(), poll_req.candidates.clone());
+    poll.seats = poll_req.seats;
     info!("Created poll: {:#?}", poll);
 
     let poll_id = poll.id.clone();
+    let admin_key = poll.admin_key().to_owned();
     poll_db.polls.write().unwrap().insert(poll_id.clone(), poll);
 
-    let admin_key: String = iter::repeat(())
-        .map(|()| rand::thread_rng().sample(rand::distributions::Alphanumeric))
-        .take(ADMIN_KEY_LENGTH)
-        .collect();
     let res = json!({
         "id": poll_id.clone(),
         "admin_key": admin_key,
@@ -75,6 +68,9 @@ pub fn vote(
 ) -> Result<(), Status> {
     let mut poll_db_lock = poll_db.polls.write().unwrap();
     let poll = poll_db_lock.get_mut(&id).ok_or(Status::NotFound)?;
+    if !poll.in_progress {
+        return Err(Status::Forbidden);
+    }
     let cookie_name = format!("voted_{}", id);
     if cookies.get_private(&cookie_name).is_some() {
         return Err(Status::Forbidden);
@@ -84,9 +80,19 @@ pub fn vote(
     Ok(())
 }
 
-#[post("/polls/<id>/results")]
-pub fn close_poll(id: String) {
-    unimplemented!()
+#[post("/polls/<id>/results", data = "<close_req>")]
+pub fn close_poll(
+    id: String,
+    close_req: Json<PollCloseRequest>,
+    poll_db: State<PollDb>,
+) -> Result<(), Status> {
+    let mut poll_db_lock = poll_db.polls.write().unwrap();
+    let poll = poll_db_lock.get_mut(&id).ok_or(Status::NotFound)?;
+    if poll.admin_key() != close_req.admin_key {
+        return Err(Status::Forbidden);
+    }
+    poll.close();
+    Ok(())
 }
 
 #[get("/polls/<id>/results")]
@@ -94,6 +100,40 @@ pub fn get_results(id: String) {
     unimplemented!()
 }
 
+/// The ballots of a closed poll in BLT format, so the count can be checked with other software.
+#[get("/polls/<id>/blt")]
+pub fn get_blt(id: String, poll_db: State<PollDb>) -> Result<content::Plain<String>, Status> {
+    let poll_db_lock = poll_db.polls.read().unwrap();
+    let poll = poll_db_lock.get(&id).ok_or(Status::NotFound)?;
+    if poll.in_progress {
+        return Err(Status::Forbidden);
+    }
+    let mut blt = Vec::new();
+    poll.election()
+        .and_then(|election| election.write_blt(&mut blt))
+        .map_err(|_| Status::UnprocessableEntity)?;
+    String::from_utf8(blt)
+        .map(content::Plain)
+        .map_err(|_| Status::InternalServerError)
+}
+
+/// The results of a closed poll as an HTML page, which can be saved and viewed offline.
+#[get("/polls/<id>/report")]
+pub fn get_report(id: String, poll_db: State<PollDb>) -> Result<content::Html<String>, Status> {
+    let poll_db_lock = poll_db.polls.read().unwrap();
+    let poll = poll_db_lock.get(&id).ok_or(Status::NotFound)?;
+    if poll.in_progress {
+        return Err(Status::Forbidden);
+    }
+    let mut html = Vec::new();
+    poll.election()
+        .and_then(|election| election.write_html(&mut html))
+        .map_err(|_| Status::UnprocessableEntity)?;
+    String::from_utf8(html)
+        .map(content::Html)
+        .map_err(|_| Status::InternalServerError)
+}
+
 #[catch(500)]
 pub fn internal_error() -> &'static str {
     "Whoops! Looks like we messed up."
@@ -130,3 +130,89 @@ fn get_nonexistent_poll_should_fail() {
     let response = client.get("/polls/foobar").dispatch();
     assert_eq!(Status::NotFound, response.status());
 }
+
+#[test]
+fn close_poll_and_get_blt() {
+    let req = json!({
+        "name": "Test poll.",
+        "candidates": ["a", "b", "c"],
+        "seats": 2
+    });
+    let client = Client::new(rocket()).expect("valid rocket instance");
+    let mut response = client
+        .post("/polls")
+        .header(ContentType::JSON)
+        .body(req.to_string())
+        .dispatch();
+    assert_eq!(Status::Created, response.status());
+    let body = serde_json::from_str::<Value>(&response.body_string().unwrap()).unwrap();
+    let id = body["id"].as_str().unwrap().to_owned();
+    let admin_key = body["admin_key"].as_str().unwrap().to_owned();
+
+    let vote = json!(["b", "a"]);
+    let response_two = client
+        .post(format!("/polls/{}", id))
+        .body(vote.to_string())
+        .dispatch();
+    assert_eq!(Status::Ok, response_two.status());
+
+    let response_three = client.get(format!("/polls/{}/blt", id)).dispatch();
+    assert_eq!(Status::Forbidden, response_three.status());
+
+    let response_four = client
+        .post(format!("/polls/{}/results", id))
+        .header(ContentType::JSON)
+        .body(json!({ "admin_key": "wrong" }).to_string())
+        .dispatch();
+    assert_eq!(Status::Forbidden, response_four.status());
+
+    let response_five = client
+        .post(format!("/polls/{}/results", id))
+        .header(ContentType::JSON)
+        .body(json!({ "admin_key": admin_key }).to_string())
+        .dispatch();
+    assert_eq!(Status::Ok, response_five.status());
+
+    let mut response_six = client.get(format!("/polls/{}/blt", id)).dispatch();
+    assert_eq!(Status::Ok, response_six.status());
+    assert_eq!(
+        "3 2\n1 2 1 0\n0\n\"a\"\n\"b\"\n\"c\"\n\"Test poll.\"\n",
+        response_six.body_string().unwrap()
+    );
+}
+
+#[test]
+fn close_poll_and_get_report() {
+    let req = json!({
+        "name": "Test <poll>.",
+        "candidates": ["a", "b", "c"]
+    });
+    let client = Client::new(rocket()).expect("valid rocket instance");
+    let mut response = client
+        .post("/polls")
+        .header(ContentType::JSON)
+        .body(req.to_string())
+        .dispatch();
+    let body = serde_json::from_str::<Value>(&response.body_string().unwrap()).unwrap();
+    let id = body["id"].as_str().unwrap().to_owned();
+    let admin_key = body["admin_key"].as_str().unwrap().to_owned();
+    client
+        .post(format!("/polls/{}", id))
+        .body(json!(["b", "a"]).to_string())
+        .dispatch();
+
+    let response_two = client.get(format!("/polls/{}/report", id)).dispatch();
+    assert_eq!(Status::Forbidden, response_two.status());
+
+    client
+        .post(format!("/polls/{}/results", id))
+        .header(ContentType::JSON)
+        .body(json!({ "admin_key": admin_key }).to_string())
+        .dispatch();
+    let mut response_three = client.get(format!("/polls/{}/report", id)).dispatch();
+    assert_eq!(Status::Ok, response_three.status());
+    assert_eq!(Some(ContentType::HTML), response_three.content_type());
+    let html = response_three.body_string().unwrap();
+    assert!(html.contains("<h1>Test &lt;poll&gt;.</h1>"));
+    assert!(html.contains("<li>b with 1 votes at stage 1</li>"));
+}
@@ -0,0 +1,409 @@
+/* good_stv - a good single transferable vote utility.
+ * Copyright (C) 2019 good_stv authors
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>. */
+
+//! Reading and writing ballots in the BLT format used by OpenSTV, Droop and eSTV.
+//!
+//! A BLT file looks like this:
+//!
+//! ```text
+//! 4 2
+//! -4
+//! 3 1 3 0
+//! 1 2 0
+//! 0
+//! "Alice"
+//! "Bob"
+//! "Charlie"
+//! "Dave"
+//! "Committee election"
+//! ```
+//!
+//! The first line gives the number of candidates and seats. It may be followed by a line of
+//! negative numbers, listing candidates who have withdrawn. Each ballot line gives the number of
+//! identical ballots, then the candidates' numbers in order of preference, ending with `0`. A
+//! line containing only `0` ends the ballots, and is followed by the name of each candidate and
+//! the title of the election.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+
+use failure::*;
+
+use crate::{BallotBox, BallotBoxBuilder, Counter};
+
+// The most candidates a BLT file may declare. Space for every candidate is set aside as soon as
+// the header is read, so a corrupt or hostile header mustn't be able to ask for an absurd amount.
+const MAX_CANDIDATES: usize = 100_000;
+
+impl BallotBox {
+    /// Construct a `BallotBox` given a path to a BLT file.
+    pub fn from_blt_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let file = File::open(&path)
+            .context(format!("Error opening file {:?}", path.as_ref().display()))?;
+        BallotBox::from_blt_reader(file)
+    }
+
+    /// Construct a `BallotBox` given any implementation of [`std::io::Read`] which contains BLT
+    /// data.
+    ///
+    /// The number of seats and the title from the file are available from
+    /// [`BallotBox::seats`] and [`BallotBox::title`]. Withdrawn candidates are removed from the
+    /// ballots and listed in [`BallotBox::withdrawn`].
+    pub fn from_blt_reader<R: Read>(reader: R) -> Result<Self, Error> {
+        let mut lines = Lines::new(BufReader::new(reader));
+
+        let header = lines
+            .next_line()?
+            .ok_or_else(|| format_err!("BLT file is empty."))?;
+        let header: Vec<&str> = header.split_whitespace().collect();
+        if header.len() != 2 {
+            return Err(lines.error("expected the number of candidates and seats"));
+        }
+        let num_candidates = lines.number(header[0])?;
+        if num_candidates > MAX_CANDIDATES as u64 {
+            return Err(lines.error(&format!(
+                "{} candidates is more than the limit of {}",
+                num_candidates, MAX_CANDIDATES
+            )));
+        }
+        let num_candidates = num_candidates as usize;
+        let seats = lines.number(header[1])?;
+
+        let mut withdrawn = vec![false; num_candidates + 1];
+        let mut line = lines.next_ballot_line()?;
+        if line.trim_start().starts_with('-') {
+            for token in line.split_whitespace() {
+                let candidate = lines.candidate(token.trim_start_matches('-'), num_candidates)?;
+                withdrawn[candidate] = true;
+            }
+            line = lines.next_ballot_line()?;
+        }
+
+        // Candidates are numbered from 1 in the file, and are only named after the ballots, so
+        // they are known by their numbers until then.
+        let numbers: Vec<String> = (0..=num_candidates).map(|i| i.to_string()).collect();
+        let running = (1..=num_candidates)
+            .filter(|&i| !withdrawn[i])
+            .map(|i| numbers[i].clone())
+            .collect();
+        let mut builder = BallotBoxBuilder::new(running)?;
+        let mut vote: Vec<&str> = Vec::new();
+        loop {
+            let mut tokens = line.split_whitespace().peekable();
+            // Some programs label each ballot line with an identifier in brackets.
+            if tokens.peek().map(|t| t.starts_with('(')) == Some(true) {
+                tokens.next();
+            }
+            let weight = match tokens.next() {
+                Some(weight) => lines.number(weight)?,
+                None => return Err(lines.error("expected a ballot")),
+            };
+            if weight == 0 {
+                break;
+            }
+            vote.clear();
+            let mut terminated = false;
+            for token in tokens {
+                if terminated {
+                    return Err(lines.error("unexpected preferences after the closing 0"));
+                }
+                if token == "0" {
+                    terminated = true;
+                    continue;
+                }
+                if token.contains('=') {
+                    return Err(lines.error("equal preferences are not supported"));
+                }
+                let candidate = lines.candidate(token, num_candidates)?;
+                if !withdrawn[candidate] {
+                    vote.push(&numbers[candidate]);
+                }
+            }
+            if !terminated {
+                return Err(lines.error("ballot must end with 0"));
+            }
+            builder.push_weighted(&vote, weight);
+            line = lines.next_ballot_line()?;
+        }
+
+        let mut rest = String::new();
+        lines
+            .reader
+            .read_to_string(&mut rest)
+            .context("Error reading BLT file.")?;
+        let mut strings = Strings(&rest);
+        let mut running = Vec::new();
+        let mut withdrawn_names = Vec::new();
+        for &is_withdrawn in &withdrawn[1..] {
+            let name = strings
+                .next()
+                .ok_or_else(|| {
+                    format_err!("BLT file names fewer than {} candidates.", num_candidates)
+                })?
+                .to_owned();
+            if is_withdrawn {
+                withdrawn_names.push(name);
+            } else {
+                running.push(name);
+            }
+        }
+        builder.rename_candidates(running)?;
+        builder.set_withdrawn(withdrawn_names);
+        builder.set_seats(seats);
+        if let Some(title) = strings.next() {
+            builder.set_title(title.to_owned());
+        }
+
+        Ok(builder.build())
+    }
+}
+
+impl<'a> Counter<'a> {
+    /// Write the election as a BLT file, so that it can be counted by other software.
+    ///
+    /// The ballots are written as they will be counted, so spoiled ballots are left out and
+    /// truncated ballots are written truncated. Withdrawn candidates are listed after the running
+    /// candidates.
+    pub fn write_blt<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        let ballot_box = self.ballot_box();
+        let candidates = ballot_box.candidates();
+        let withdrawn = ballot_box.withdrawn();
+        for name in candidates.iter().chain(withdrawn) {
+            if name.contains('"') {
+                return Err(format_err!(
+                    "Candidate {:?} can't be written to a BLT file as the name contains a quote.",
+                    name
+                ));
+            }
+        }
+
+        writeln!(
+            writer,
+            "{} {}",
+            candidates.len() + withdrawn.len(),
+            self.config().seats
+        )?;
+        if !withdrawn.is_empty() {
+            let ids: Vec<String> = (1..=withdrawn.len())
+                .map(|i| format!("-{}", candidates.len() + i))
+                .collect();
+            writeln!(writer, "{}", ids.join(" "))?;
+        }
+        for &(preferences, count) in self.valid_ballots() {
+            write!(writer, "{}", count)?;
+            for id in preferences {
+                write!(writer, " {}", id + 1)?;
+            }
+            writeln!(writer, " 0")?;
+        }
+        writeln!(writer, "0")?;
+        for name in candidates.iter().chain(withdrawn) {
+            writeln!(writer, "\"{}\"", name)?;
+        }
+        writeln!(
+            writer,
+            "\"{}\"",
+            ballot_box.title().unwrap_or("").replace('"', "'")
+        )?;
+        Ok(())
+    }
+}
+
+// Reads non-blank lines, keeping track of the line number for error messages.
+struct Lines<R> {
+    reader: R,
+    number: usize,
+}
+
+impl<R: BufRead> Lines<R> {
+    fn new(reader: R) -> Self {
+        Lines { reader, number: 0 }
+    }
+
+    fn next_line(&mut self) -> Result<Option<String>, Error> {
+        loop {
+            let mut line = String::new();
+            self.number += 1;
+            let read = self
+                .reader
+                .read_line(&mut line)
+                .context("Error reading BLT file.")?;
+            if read == 0 {
+                return Ok(None);
+            }
+            if !line.trim().is_empty() {
+                return Ok(Some(line));
+            }
+        }
+    }
+
+    fn next_ballot_line(&mut self) -> Result<String, Error> {
+        self.next_line()?
+            .ok_or_else(|| format_err!("BLT file ended before the end of the ballots."))
+    }
+
+    fn error(&self, message: &str) -> Error {
+        format_err!("Line {} of BLT file: {}.", self.number, message)
+    }
+
+    fn number(&self, token: &str) -> Result<u64, Error> {
+        token
+            .parse()
+            .map_err(|_| self.error(&format!("{:?} is not a whole number", token)))
+    }
+
+    fn candidate(&self, token: &str, num_candidates: usize) -> Result<usize, Error> {
+        let candidate = self.number(token)? as usize;
+        if candidate == 0 || candidate > num_candidates {
+            return Err(self.error(&format!("there is no candidate {}", candidate)));
+        }
+        Ok(candidate)
+    }
+}
+
+// Splits the end of a BLT file into names, which are usually quoted but may be bare words.
+struct Strings<'s>(&'s str);
+
+impl<'s> Iterator for Strings<'s> {
+    type Item = &'s str;
+
+    fn next(&mut self) -> Option<&'s str> {
+        let rest = self.0.trim_start();
+        if rest.is_empty() {
+            return None;
+        }
+        let (string, rest) = if let Some(quoted) = rest.strip_prefix('"') {
+            let end = quoted.find('"').unwrap_or(quoted.len());
+            (&quoted[..end], quoted.get(end + 1..).unwrap_or(""))
+        } else {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            (&rest[..end], &rest[end..])
+        };
+        self.0 = rest;
+        Some(string)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Counter, ElectionConfig, ElectionError};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_read_blt() {
+        let test_blt = "4 2\n-4\n3 1 3 0\n1 2 0\n(a) 2 2 4 1 0\n\n0\n\
+                        \"Alice\"\n\"Bob\"\n\"Charlie\"\n\"Dave\"\n\"Committee election\"\n";
+
+        let ballot_box = BallotBox::from_blt_reader(Cursor::new(test_blt)).unwrap();
+
+        assert_eq!(&["Alice", "Bob", "Charlie"], ballot_box.candidates());
+        assert_eq!(&["Dave"], ballot_box.withdrawn());
+        assert_eq!(Some("Committee election"), ballot_box.title());
+        assert_eq!(Some(2), ballot_box.seats());
+        assert_eq!(6, ballot_box.num_votes());
+        assert_eq!(
+            vec![(vec![0, 2], 3), (vec![1], 1), (vec![1, 0], 2)],
+            ballot_box
+                .ballots()
+                .iter()
+                .map(|b| (b.preferences().to_vec(), b.count()))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_count_blt() {
+        let test_blt = "3 1\n4 1 2 0\n3 2 0\n2 3 2 0\n0\n\"a\" \"b\" \"c\"\n";
+        let ballot_box = BallotBox::from_blt_reader(Cursor::new(test_blt)).unwrap();
+        let config = ElectionConfig::new(1).seed(0);
+
+        let results = Counter::new(&ballot_box, config)
+            .unwrap()
+            .results()
+            .unwrap();
+
+        assert_eq!(Some(&5.0), results.elected().get("b"));
+        assert_eq!(None, ballot_box.title());
+    }
+
+    #[test]
+    fn test_write_blt() {
+        let test_blt = "4 2\n-4\n3 1 3 0\n1 2 0\n2 2 4 1 0\n0\n\"Alice\"\n\"Bob\"\n\"Charlie\"\n\
+                        \"Dave\"\n\"Committee election\"\n";
+        let ballot_box = BallotBox::from_blt_reader(Cursor::new(test_blt)).unwrap();
+        let counter = Counter::new(&ballot_box, ElectionConfig::new(2)).unwrap();
+
+        let mut blt = Vec::new();
+        counter.write_blt(&mut blt).unwrap();
+
+        assert_eq!(
+            "4 2\n-4\n3 1 3 0\n1 2 0\n2 2 1 0\n0\n\"Alice\"\n\"Bob\"\n\"Charlie\"\n\"Dave\"\n\
+             \"Committee election\"\n",
+            String::from_utf8(blt.clone()).unwrap()
+        );
+        assert_eq!(
+            ballot_box,
+            BallotBox::from_blt_reader(Cursor::new(blt)).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_write_csv_election_as_blt() {
+        let test_csv = "a,b,c\na,b\nb\na,b\nz,a\n";
+        let ballot_box = BallotBox::from_reader(Cursor::new(test_csv)).unwrap();
+        let counter = Counter::new(&ballot_box, ElectionConfig::new(1)).unwrap();
+
+        let mut blt = Vec::new();
+        counter.write_blt(&mut blt).unwrap();
+
+        assert_eq!(
+            "3 1\n2 1 2 0\n1 2 0\n0\n\"a\"\n\"b\"\n\"c\"\n\"\"\n",
+            String::from_utf8(blt).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_blt_errors() {
+        let unknown = BallotBox::from_blt_reader(Cursor::new("2 1\n1 3 0\n0\n\"a\"\n\"b\"\n"));
+        assert_eq!(
+            "Line 2 of BLT file: there is no candidate 3.",
+            unknown.unwrap_err().to_string()
+        );
+
+        let unterminated = BallotBox::from_blt_reader(Cursor::new("2 1\n1 1 2\n0\n\"a\"\n\"b\"\n"));
+        assert_eq!(
+            "Line 2 of BLT file: ballot must end with 0.",
+            unterminated.unwrap_err().to_string()
+        );
+
+        let huge = BallotBox::from_blt_reader(Cursor::new("4294967295 1\n1 1 0\n0\n"));
+        assert_eq!(
+            "Line 1 of BLT file: 4294967295 candidates is more than the limit of 100000.",
+            huge.unwrap_err().to_string()
+        );
+
+        let missing_name = BallotBox::from_blt_reader(Cursor::new("2 1\n1 1 0\n0\n\"a\"\n"));
+        assert!(missing_name.is_err());
+
+        let duplicate = BallotBox::from_blt_reader(Cursor::new("2 1\n1 1 0\n0\n\"a\"\n\"a\"\n"));
+        assert_eq!(
+            Some(&ElectionError::DuplicateCandidate("a".to_owned())),
+            duplicate.unwrap_err().downcast_ref::<ElectionError>()
+        );
+    }
+}
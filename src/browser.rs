@@ -0,0 +1,387 @@
+/* good_stv - a good single transferable vote utility.
+ * Copyright (C) 2019 good_stv authors
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>. */
+
+//! Stepping backwards and forwards through a finished count.
+
+use std::io::Write;
+
+use failure::*;
+
+use crate::count_sheet::format_votes;
+use crate::{Candidate, Counter, ElectionError, ElectionResults, Round};
+
+/// A position in a finished count, which can be moved between stages to show the state of the
+/// count at each one.
+///
+/// Each stage is shown as it stood at the start of that stage, along with what happened during
+/// it.
+#[derive(Debug)]
+pub struct CountBrowser<'c, 'a> {
+    counter: &'c Counter<'a>,
+    results: &'c ElectionResults,
+    // Index into `results.rounds()`.
+    index: usize,
+}
+
+impl<'c, 'a> CountBrowser<'c, 'a> {
+    /// Start browsing `results` at the first stage.
+    pub fn new(counter: &'c Counter<'a>, results: &'c ElectionResults) -> Self {
+        CountBrowser {
+            counter,
+            results,
+            index: 0,
+        }
+    }
+
+    /// The number of the stage being shown, starting from 1.
+    pub fn stage(&self) -> u64 {
+        self.index as u64 + 1
+    }
+
+    /// The number of stages in the count.
+    pub fn num_stages(&self) -> u64 {
+        self.results.rounds().len() as u64
+    }
+
+    /// The stage being shown, or `None` if the count had no stages.
+    pub fn round(&self) -> Option<&'c Round> {
+        self.results.rounds().get(self.index)
+    }
+
+    /// Move to the next stage. Returns `false` if this was the last stage.
+    pub fn forward(&mut self) -> bool {
+        self.go_to(self.stage() + 1)
+    }
+
+    /// Move to the previous stage. Returns `false` if this was the first stage.
+    pub fn back(&mut self) -> bool {
+        self.stage() > 1 && self.go_to(self.stage() - 1)
+    }
+
+    /// Move to stage `stage`, counting from 1. Returns `false`, without moving, if there is no
+    /// such stage.
+    pub fn go_to(&mut self, stage: u64) -> bool {
+        if stage >= 1 && stage <= self.num_stages() {
+            self.index = stage as usize - 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Find a running candidate by their name or display name, ignoring case.
+    pub fn find_candidate(&self, name: &str) -> Option<&'a Candidate> {
+        let ballot_box = self.counter.ballot_box();
+        let name = name.trim().to_lowercase();
+        ballot_box.candidates().iter().find(|candidate| {
+            candidate.to_lowercase() == name
+                || ballot_box.display_name(candidate).to_lowercase() == name
+        })
+    }
+
+    /// Write every candidate's votes at the start of the stage, and when anyone was elected or
+    /// excluded, followed by a description of what happened during the stage.
+    pub fn write_stage<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        let round = match self.round() {
+            Some(round) => round,
+            None => {
+                writeln!(writer, "The count had no stages.")?;
+                return Ok(());
+            }
+        };
+        let ballot_box = self.counter.ballot_box();
+        let precision = self.counter.config().precision;
+        writeln!(
+            writer,
+            "Stage {} of {}. Quota: {}",
+            self.stage(),
+            self.num_stages(),
+            format_votes(self.counter.quota(), precision)
+        )?;
+        writeln!(writer)?;
+
+        let rows: Vec<(&str, String, String)> = ballot_box
+            .candidates()
+            .iter()
+            .map(|candidate| {
+                let votes = round
+                    .tally(candidate)
+                    .map(|votes| format_votes(votes, precision))
+                    .unwrap_or_default();
+                (
+                    ballot_box.display_name(candidate),
+                    votes,
+                    self.outcome(candidate),
+                )
+            })
+            .collect();
+        let name_width = rows
+            .iter()
+            .map(|(name, _, _)| name.chars().count())
+            .max()
+            .unwrap_or_default();
+        let votes_width = rows
+            .iter()
+            .map(|(_, votes, _)| votes.len())
+            .max()
+            .unwrap_or_default();
+        for (name, votes, outcome) in rows {
+            let line = format!(
+                "  {:<name_width$}  {:>votes_width$}  {}",
+                name,
+                votes,
+                outcome,
+                name_width = name_width,
+                votes_width = votes_width
+            );
+            writeln!(writer, "{}", line.trim_end())?;
+        }
+        writeln!(writer)?;
+        self.counter.write_narrative(round, writer)
+    }
+
+    /// Write where the votes on `candidate`'s pile at the start of the stage came from: their
+    /// first preferences and the votes transferred to them at each earlier stage, less any they
+    /// passed on after being elected or excluded. As in the [`CountSheet`](crate::CountSheet),
+    /// votes left over when a surplus is rounded down stay on the pile.
+    ///
+    /// Returns an [`ElectionError`] if `candidate` isn't running.
+    pub fn write_pile<W: Write>(&self, candidate: &str, mut writer: W) -> Result<(), Error> {
+        let candidate = self
+            .find_candidate(candidate)
+            .ok_or_else(|| ElectionError::UnknownCandidate(candidate.to_owned()))?;
+        let ballot_box = self.counter.ballot_box();
+        let precision = self.counter.config().precision;
+        let rounds = &self.results.rounds()[..self.index];
+
+        let mut sources = vec![(
+            "First preferences".to_owned(),
+            self.results
+                .rounds()
+                .first()
+                .and_then(|round| round.tally(candidate))
+                .unwrap_or_default(),
+        )];
+        for round in rounds {
+            for transfer in round.transfers() {
+                if transfer.destination() == Some(candidate) {
+                    sources.push((
+                        format!(
+                            "From {} at stage {}",
+                            ballot_box.display_name(transfer.source()),
+                            round.number()
+                        ),
+                        transfer.votes(),
+                    ));
+                }
+            }
+            let transferred: f64 = round
+                .transfers()
+                .iter()
+                .filter(|transfer| transfer.source() == candidate)
+                .map(|transfer| transfer.votes())
+                .sum();
+            if transferred > 0.0 {
+                sources.push((
+                    format!("To others at stage {}", round.number()),
+                    -transferred,
+                ));
+            }
+        }
+        let total: f64 = sources.iter().map(|&(_, votes)| votes).sum();
+        sources.push(("Total".to_owned(), total));
+
+        writeln!(
+            writer,
+            "Pile of {} at the start of stage {}:",
+            ballot_box.display_name(candidate),
+            self.stage()
+        )?;
+        let label_width = sources
+            .iter()
+            .map(|(label, _)| label.chars().count())
+            .max()
+            .unwrap_or_default();
+        let sources: Vec<(String, String)> = sources
+            .into_iter()
+            .map(|(label, votes)| (label, format_votes(votes, precision)))
+            .collect();
+        let votes_width = sources
+            .iter()
+            .map(|(_, votes)| votes.len())
+            .max()
+            .unwrap_or_default();
+        for (label, votes) in sources {
+            writeln!(
+                writer,
+                "  {:<label_width$}  {:>votes_width$}",
+                label,
+                votes,
+                label_width = label_width,
+                votes_width = votes_width
+            )?;
+        }
+        let outcome = self.outcome(candidate);
+        if !outcome.is_empty() {
+            writeln!(writer, "  {}", outcome)?;
+        }
+        Ok(())
+    }
+
+    // Describes when `candidate` was elected or excluded, relative to the stage being shown.
+    fn outcome(&self, candidate: &str) -> String {
+        for round in self.results.rounds() {
+            let verb = if round.elected().iter().any(|name| name == candidate) {
+                "Elected"
+            } else if round.excluded().map(String::as_str) == Some(candidate) {
+                "Excluded"
+            } else {
+                continue;
+            };
+            return match round.number() {
+                stage if stage < self.stage() => format!("{} at stage {}", verb, stage),
+                stage if stage == self.stage() => format!("{} at this stage", verb),
+                _ => String::new(),
+            };
+        }
+        String::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CountSheet, Election, ElectionConfig, Method};
+    use std::io::Cursor;
+
+    const TEST_CSV: &str = "a,b,c,d\na,b\na,b\na,c\na\nb,c\nc\nc\nd,c\n";
+
+    #[test]
+    fn test_stepping() {
+        let config = ElectionConfig::new(2).method(Method::Gregory).seed(0);
+        let election = Election::from_reader(Cursor::new(TEST_CSV), config).unwrap();
+        let counter = election.counter();
+        let results = counter.results().unwrap();
+        let mut browser = CountBrowser::new(&counter, &results);
+
+        assert_eq!(3, browser.num_stages());
+        assert!(!browser.back());
+        assert!(browser.forward());
+        assert!(browser.forward());
+        assert!(!browser.forward());
+        assert_eq!(3, browser.stage());
+        assert!(!browser.go_to(0));
+        assert!(browser.go_to(2));
+        assert_eq!(Some(&"d".to_owned()), browser.round().unwrap().excluded());
+        assert_eq!(Some(&"c".to_owned()), browser.find_candidate(" C "));
+    }
+
+    #[test]
+    fn test_write_stage_and_pile() {
+        let config = ElectionConfig::new(2).method(Method::Gregory).seed(0);
+        let election = Election::from_reader(Cursor::new(TEST_CSV), config).unwrap();
+        let counter = election.counter();
+        let results = counter.results().unwrap();
+        let mut browser = CountBrowser::new(&counter, &results);
+        browser.go_to(3);
+        let mut text = Vec::new();
+        browser.write_stage(&mut text).unwrap();
+        browser.write_pile("c", &mut text).unwrap();
+
+        assert_eq!(
+            "Stage 3 of 3. Quota: 3
+
+  a        Elected at stage 1
+  b   1.5
+  c  3.25  Elected at this stage
+  d        Excluded at stage 2
+
+Stage 3:
+  c is elected with 3.25 votes, a surplus of 0.25 votes.
+  0.25 votes from c: 0.22 non-transferable, 0.03 kept by c after rounding.
+Pile of c at the start of stage 3:
+  First preferences     2
+  From a at stage 1  0.25
+  From d at stage 2     1
+  Total              3.25
+  Elected at this stage
+",
+            String::from_utf8(text).unwrap()
+        );
+        assert!(browser.write_pile("z", Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_pile_after_transfers() {
+        let config = ElectionConfig::new(2).method(Method::Gregory).seed(0);
+        let election = Election::from_reader(Cursor::new(TEST_CSV), config).unwrap();
+        let counter = election.counter();
+        let results = counter.results().unwrap();
+        let mut browser = CountBrowser::new(&counter, &results);
+        browser.go_to(3);
+        let mut text = Vec::new();
+        browser.write_pile("d", &mut text).unwrap();
+        browser.write_pile("a", &mut text).unwrap();
+
+        // d's vote went to c when they were excluded, and a kept the quota of 3 after their
+        // surplus was transferred.
+        assert_eq!(
+            "Pile of d at the start of stage 3:
+  First preferences      1
+  To others at stage 2  -1
+  Total                  0
+  Excluded at stage 2
+Pile of a at the start of stage 3:
+  First preferences      4
+  To others at stage 1  -1
+  Total                  3
+  Elected at stage 1
+",
+            String::from_utf8(text).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_pile_with_rounding() {
+        let test_csv = "a,b,c\na,b\na,b\na,b\na,b\na,b\na,b\na\nb\nc,b\nc,b\nc,b\n";
+        let config = ElectionConfig::new(2)
+            .method(Method::Gregory)
+            .precision(1)
+            .seed(0);
+        let election = Election::from_reader(Cursor::new(test_csv), config).unwrap();
+        let counter = election.counter();
+        let results = counter.results().unwrap();
+        let mut browser = CountBrowser::new(&counter, &results);
+        let mut text = Vec::new();
+        browser.forward();
+        browser.write_pile("a", &mut text).unwrap();
+
+        // With one decimal place a's surplus of 3 is transferred at 0.4 a ballot, so 0.2 more
+        // than the quota of 4 stays with a, as it does in the count sheet.
+        assert_eq!(4.0, counter.quota());
+        assert_eq!(
+            "Pile of a at the start of stage 2:
+  First preferences        7
+  To others at stage 1  -2.8
+  Total                  4.2
+  Elected at stage 1
+",
+            String::from_utf8(text).unwrap()
+        );
+        let sheet = CountSheet::new(&counter, &results);
+        assert_eq!(Some(4.2), sheet.rows()[0].cells()[2]);
+    }
+}
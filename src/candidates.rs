@@ -0,0 +1,313 @@
+/* good_stv - a good single transferable vote utility.
+ * Copyright (C) 2019 good_stv authors
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>. */
+
+//! Candidates listed in a file of their own, apart from the ballots.
+//!
+//! A CSV list has a header naming its columns. Only `name` is required:
+//!
+//! ```text
+//! name,display_name,party,category,withdrawn
+//! alice,Alice Smith,Green,,
+//! bob,Bob Jones,,Student,yes
+//! ```
+//!
+//! JSON and TOML lists hold the same fields under `candidates`:
+//!
+//! ```toml
+//! [[candidates]]
+//! name = "alice"
+//! party = "Green"
+//! ```
+
+use std::fmt;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+
+use csv::{ReaderBuilder, Trim};
+use failure::*;
+use serde::de::{self, Deserializer, Visitor};
+use serde_derive::Deserialize;
+
+use crate::Candidate;
+
+/// A candidate, and what is known about them apart from their name.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct CandidateInfo {
+    name: Candidate,
+    #[serde(default)]
+    display_name: Option<String>,
+    #[serde(default)]
+    party: Option<String>,
+    #[serde(default)]
+    category: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_flag")]
+    withdrawn: bool,
+}
+
+impl CandidateInfo {
+    /// Create a running candidate with no other details.
+    pub fn new<S: Into<Candidate>>(name: S) -> Self {
+        CandidateInfo {
+            name: name.into(),
+            display_name: None,
+            party: None,
+            category: None,
+            withdrawn: false,
+        }
+    }
+
+    /// Set the name shown for the candidate in reports.
+    pub fn with_display_name<S: Into<String>>(mut self, display_name: S) -> Self {
+        self.display_name = Some(display_name.into());
+        self
+    }
+
+    /// Set the party or slate the candidate belongs to.
+    pub fn with_party<S: Into<String>>(mut self, party: S) -> Self {
+        self.party = Some(party.into());
+        self
+    }
+
+    /// Set the category the candidate is standing in.
+    pub fn with_category<S: Into<String>>(mut self, category: S) -> Self {
+        self.category = Some(category.into());
+        self
+    }
+
+    /// Mark the candidate as having withdrawn before the count.
+    pub fn with_withdrawn(mut self, withdrawn: bool) -> Self {
+        self.withdrawn = withdrawn;
+        self
+    }
+
+    /// The name used for the candidate on ballots.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The name shown for the candidate in reports, which defaults to [`CandidateInfo::name`].
+    pub fn display_name(&self) -> &str {
+        self.display_name.as_deref().unwrap_or(&self.name)
+    }
+
+    /// The party or slate the candidate belongs to, if any.
+    pub fn party(&self) -> Option<&str> {
+        self.party.as_deref()
+    }
+
+    /// The category the candidate is standing in, if any.
+    pub fn category(&self) -> Option<&str> {
+        self.category.as_deref()
+    }
+
+    /// Whether the candidate withdrew before the count.
+    pub fn is_withdrawn(&self) -> bool {
+        self.withdrawn
+    }
+}
+
+/// The candidates in an election, in the order they are listed on ballots.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct CandidateList {
+    candidates: Vec<CandidateInfo>,
+}
+
+impl CandidateList {
+    /// Create a list from the given candidates.
+    pub fn new(candidates: Vec<CandidateInfo>) -> Self {
+        CandidateList { candidates }
+    }
+
+    /// Read a list from a file. Files ending in `.json` are read as JSON, `.toml` as TOML, and
+    /// anything else as CSV.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let error = || format!("Error opening file {:?}", path.display());
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => {
+                let contents = fs::read_to_string(path).context(error())?;
+                Ok(serde_json::from_str(&contents).context("Could not parse candidate list.")?)
+            }
+            Some("toml") => {
+                let contents = fs::read_to_string(path).context(error())?;
+                Ok(toml::from_str(&contents).context("Could not parse candidate list.")?)
+            }
+            _ => CandidateList::from_csv_reader(File::open(path).context(error())?),
+        }
+    }
+
+    /// Read a list from CSV data with a header naming its columns, one of which must be `name`.
+    pub fn from_csv_reader<R: Read>(reader: R) -> Result<Self, Error> {
+        let mut csv_reader = ReaderBuilder::new().trim(Trim::All).from_reader(reader);
+        let mut candidates = Vec::new();
+        for record in csv_reader.deserialize() {
+            let candidate: CandidateInfo = record.context("Could not parse candidate list.")?;
+            candidates.push(candidate);
+        }
+        Ok(CandidateList { candidates })
+    }
+
+    /// Every candidate in the list, including any who have withdrawn.
+    pub fn candidates(&self) -> &[CandidateInfo] {
+        &self.candidates
+    }
+
+    /// The details of the candidate called `name`, if they are in the list.
+    pub fn get(&self, name: &str) -> Option<&CandidateInfo> {
+        self.candidates
+            .iter()
+            .find(|candidate| candidate.name == name)
+    }
+
+    /// The names of the candidates who are still running.
+    pub fn running(&self) -> Vec<Candidate> {
+        self.names(false)
+    }
+
+    /// The names of the candidates who have withdrawn.
+    pub fn withdrawn(&self) -> Vec<Candidate> {
+        self.names(true)
+    }
+
+    fn names(&self, withdrawn: bool) -> Vec<Candidate> {
+        self.candidates
+            .iter()
+            .filter(|candidate| candidate.withdrawn == withdrawn)
+            .map(|candidate| candidate.name.clone())
+            .collect()
+    }
+}
+
+// Reads the withdrawn flag, which spreadsheets tend to write as "yes", "1", "x" or similar.
+fn deserialize_flag<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    struct FlagVisitor;
+
+    impl<'de> Visitor<'de> for FlagVisitor {
+        type Value = bool;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a boolean, or yes or no")
+        }
+
+        fn visit_bool<E: de::Error>(self, value: bool) -> Result<bool, E> {
+            Ok(value)
+        }
+
+        fn visit_u64<E: de::Error>(self, value: u64) -> Result<bool, E> {
+            Ok(value != 0)
+        }
+
+        fn visit_i64<E: de::Error>(self, value: i64) -> Result<bool, E> {
+            Ok(value != 0)
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> Result<bool, E> {
+            match value.trim().to_lowercase().as_str() {
+                "" | "no" | "n" | "false" | "0" => Ok(false),
+                "yes" | "y" | "true" | "1" | "x" => Ok(true),
+                _ => Err(E::invalid_value(de::Unexpected::Str(value), &self)),
+            }
+        }
+
+        fn visit_unit<E: de::Error>(self) -> Result<bool, E> {
+            Ok(false)
+        }
+
+        fn visit_none<E: de::Error>(self) -> Result<bool, E> {
+            Ok(false)
+        }
+    }
+
+    deserializer.deserialize_any(FlagVisitor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BallotBox, CsvDialect};
+    use std::io::Cursor;
+
+    const TEST_LIST: &str = "name,display_name,party,withdrawn
+alice,Alice Smith,Green,
+bob,,,yes
+charlie,Charlie Brown,,0
+";
+
+    #[test]
+    fn test_read_csv_list() {
+        let list = CandidateList::from_csv_reader(Cursor::new(TEST_LIST)).unwrap();
+
+        assert_eq!(
+            &[
+                CandidateInfo::new("alice")
+                    .with_display_name("Alice Smith")
+                    .with_party("Green"),
+                CandidateInfo::new("bob").with_withdrawn(true),
+                CandidateInfo::new("charlie").with_display_name("Charlie Brown"),
+            ],
+            list.candidates()
+        );
+        assert_eq!(vec!["alice", "charlie"], list.running());
+        assert_eq!(vec!["bob"], list.withdrawn());
+        assert_eq!("bob", list.get("bob").unwrap().display_name());
+    }
+
+    #[test]
+    fn test_read_toml_list() {
+        let list: CandidateList = toml::from_str(
+            r#"
+            [[candidates]]
+            name = "alice"
+            category = "Student"
+
+            [[candidates]]
+            name = "bob"
+            withdrawn = true
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(Some("Student"), list.candidates()[0].category());
+        assert_eq!(vec!["bob"], list.withdrawn());
+    }
+
+    #[test]
+    fn test_ballots_with_candidate_list() {
+        let list = CandidateList::from_csv_reader(Cursor::new(TEST_LIST)).unwrap();
+        let ballot_box = BallotBox::from_reader_with_candidates(
+            Cursor::new("alice,bob,charlie\nbob,charlie\n"),
+            list,
+            &CsvDialect::default(),
+        )
+        .unwrap();
+
+        assert_eq!(&["alice", "charlie"], ballot_box.candidates());
+        assert_eq!(&["bob"], ballot_box.withdrawn());
+        let votes: Vec<Vec<String>> = ballot_box.votes().collect();
+        assert_eq!(vec![vec!["alice", "charlie"], vec!["charlie"]], votes);
+        assert_eq!("Alice Smith", ballot_box.display_name("alice"));
+        assert_eq!("dave", ballot_box.display_name("dave"));
+    }
+
+    #[test]
+    fn test_invalid_flag() {
+        let error = CandidateList::from_csv_reader(Cursor::new("name,withdrawn\nalice,maybe\n"))
+            .unwrap_err();
+
+        assert_eq!("Could not parse candidate list.", error.to_string());
+    }
+}
@@ -0,0 +1,395 @@
+/* good_stv - a good single transferable vote utility.
+ * Copyright (C) 2019 good_stv authors
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>. */
+
+//! Rules used to count an election.
+
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use failure::*;
+use serde_derive::{Deserialize, Serialize};
+
+/// Largest supported number of decimal places for fractional transfers.
+pub const MAX_PRECISION: u32 = 9;
+
+const DEFAULT_PRECISION: u32 = 2;
+
+/// How surplus votes are transferred from an elected candidate.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Method {
+    /// A random sample of the winner's ballots, equal in size to the surplus, is transferred at
+    /// full value.
+    Random,
+    /// Every one of the winner's ballots is transferred at a fractional value of
+    /// `surplus / total`.
+    Gregory,
+}
+
+/// The formula used to calculate the number of votes needed to be elected.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Quota {
+    /// `floor(votes / (seats + 1)) + 1`
+    Droop,
+    /// `votes / seats`
+    Hare,
+}
+
+/// How to choose which candidate to exclude when several share the lowest tally.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TieBreak {
+    /// Choose one of the tied candidates at random.
+    Random,
+    /// Exclude whichever tied candidate had the fewest votes at the earliest stage where their
+    /// tallies differed, falling back to random.
+    Forwards,
+    /// Exclude whichever tied candidate had the fewest votes at the most recent stage where their
+    /// tallies differed, falling back to random.
+    Backwards,
+}
+
+/// What to do with ballots which are empty or contain a candidate who isn't running.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BallotPolicy {
+    /// Discard the whole ballot as spoiled.
+    Spoil,
+    /// Keep the preferences before the first invalid one, discarding the ballot if none remain.
+    Truncate,
+    /// Refuse to count the election.
+    Strict,
+}
+
+/// The rules used to count an election.
+///
+/// `ElectionConfig` is constructed with the number of seats, and every other option can be
+/// changed builder-style:
+///
+/// ```
+/// use good_stv::{ElectionConfig, Method, TieBreak};
+///
+/// let config = ElectionConfig::new(3)
+///     .method(Method::Gregory)
+///     .tie_break(TieBreak::Backwards)
+///     .seed(42);
+/// assert_eq!(config.seats, 3);
+/// ```
+///
+/// It can also be saved to and loaded from TOML or JSON, so that the rules of an election can be
+/// kept alongside its ballots.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ElectionConfig {
+    /// Number of seats to be filled.
+    pub seats: u64,
+    /// How surplus votes are transferred.
+    #[serde(default = "default_method")]
+    pub method: Method,
+    /// Formula for the number of votes needed to be elected.
+    #[serde(default = "default_quota")]
+    pub quota: Quota,
+    /// How ties for exclusion are broken.
+    #[serde(default = "default_tie_break")]
+    pub tie_break: TieBreak,
+    /// Seed for the random number generator, making random transfers and tie-breaks repeatable.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// What to do with invalid ballots.
+    #[serde(default = "default_ballot_policy")]
+    pub ballot_policy: BallotPolicy,
+    /// Number of decimal places kept when transferring fractional votes.
+    #[serde(default = "default_precision")]
+    pub precision: u32,
+}
+
+impl ElectionConfig {
+    /// Create a config for filling `seats` seats, with every other option left as its default.
+    pub fn new(seats: u64) -> Self {
+        ElectionConfig {
+            seats,
+            method: default_method(),
+            quota: default_quota(),
+            tie_break: default_tie_break(),
+            seed: None,
+            ballot_policy: default_ballot_policy(),
+            precision: default_precision(),
+        }
+    }
+
+    /// Set the number of seats to be filled.
+    pub fn seats(mut self, seats: u64) -> Self {
+        self.seats = seats;
+        self
+    }
+
+    /// Set how surplus votes are transferred. Defaults to [`Method::Random`].
+    pub fn method(mut self, method: Method) -> Self {
+        self.method = method;
+        self
+    }
+
+    /// Set the quota formula. Defaults to [`Quota::Droop`].
+    pub fn quota(mut self, quota: Quota) -> Self {
+        self.quota = quota;
+        self
+    }
+
+    /// Set how ties for exclusion are broken. Defaults to [`TieBreak::Random`].
+    pub fn tie_break(mut self, tie_break: TieBreak) -> Self {
+        self.tie_break = tie_break;
+        self
+    }
+
+    /// Seed the random number generator. By default it is seeded from the operating system.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Set what to do with invalid ballots. Defaults to [`BallotPolicy::Spoil`].
+    pub fn ballot_policy(mut self, ballot_policy: BallotPolicy) -> Self {
+        self.ballot_policy = ballot_policy;
+        self
+    }
+
+    /// Set the number of decimal places kept in fractional transfers. Defaults to 2.
+    pub fn precision(mut self, precision: u32) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    /// Parse a config from a TOML document.
+    pub fn from_toml(toml: &str) -> Result<Self, Error> {
+        Ok(toml::from_str(toml).context("Could not parse TOML election config.")?)
+    }
+
+    /// Serialise the config to a TOML document.
+    pub fn to_toml(&self) -> Result<String, Error> {
+        Ok(toml::to_string(self).context("Could not serialise election config to TOML.")?)
+    }
+
+    /// Parse a config from a JSON document.
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        Ok(serde_json::from_str(json).context("Could not parse JSON election config.")?)
+    }
+
+    /// Serialise the config to a JSON document.
+    pub fn to_json(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string_pretty(self)
+            .context("Could not serialise election config to JSON.")?)
+    }
+
+    /// Load a config from a file, which is read as JSON if it has a `.json` extension and as TOML
+    /// otherwise.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        ElectionConfig::from_file_with_seats(path, None)
+    }
+
+    /// Load a config from a file like [`ElectionConfig::from_file`], except that the file may leave
+    /// out `seats`, in which case `default_seats` is used. A number of seats in the file is kept.
+    pub fn from_file_with_seats<P: AsRef<Path>>(
+        path: P,
+        default_seats: Option<u64>,
+    ) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let contents =
+            fs::read_to_string(path).context(format!("Error opening file {:?}", path.display()))?;
+        if path.extension().and_then(|ext| ext.to_str()) == Some("json") {
+            ElectionConfig::from_json_with_seats(&contents, default_seats)
+        } else {
+            ElectionConfig::from_toml_with_seats(&contents, default_seats)
+        }
+    }
+
+    fn from_toml_with_seats(toml: &str, default_seats: Option<u64>) -> Result<Self, Error> {
+        let mut value: toml::Value =
+            toml::from_str(toml).context("Could not parse TOML election config.")?;
+        if let (Some(table), Some(seats)) = (value.as_table_mut(), default_seats) {
+            table
+                .entry("seats".to_owned())
+                .or_insert_with(|| toml::Value::Integer(seats as i64));
+        }
+        if value.get("seats").is_none() {
+            return Err(format_err!(
+                "The election config doesn't give the number of seats."
+            ));
+        }
+        Ok(value
+            .try_into()
+            .context("Could not parse TOML election config.")?)
+    }
+
+    fn from_json_with_seats(json: &str, default_seats: Option<u64>) -> Result<Self, Error> {
+        let mut value: serde_json::Value =
+            serde_json::from_str(json).context("Could not parse JSON election config.")?;
+        if let (Some(object), Some(seats)) = (value.as_object_mut(), default_seats) {
+            object
+                .entry("seats")
+                .or_insert_with(|| serde_json::Value::from(seats));
+        }
+        if value.get("seats").is_none() {
+            return Err(format_err!(
+                "The election config doesn't give the number of seats."
+            ));
+        }
+        Ok(serde_json::from_value(value).context("Could not parse JSON election config.")?)
+    }
+
+    // Number of fixed-point units which make up a single vote.
+    pub(crate) fn scale(&self) -> u64 {
+        10u64.pow(self.precision)
+    }
+}
+
+fn default_method() -> Method {
+    Method::Random
+}
+
+fn default_quota() -> Quota {
+    Quota::Droop
+}
+
+fn default_tie_break() -> TieBreak {
+    TieBreak::Random
+}
+
+fn default_ballot_policy() -> BallotPolicy {
+    BallotPolicy::Spoil
+}
+
+fn default_precision() -> u32 {
+    DEFAULT_PRECISION
+}
+
+impl FromStr for Method {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s {
+            "random" => Ok(Method::Random),
+            "gregory" => Ok(Method::Gregory),
+            _ => Err(format_err!("Unknown counting method {:?}.", s)),
+        }
+    }
+}
+
+impl FromStr for Quota {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s {
+            "droop" => Ok(Quota::Droop),
+            "hare" => Ok(Quota::Hare),
+            _ => Err(format_err!("Unknown quota {:?}.", s)),
+        }
+    }
+}
+
+impl FromStr for TieBreak {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s {
+            "random" => Ok(TieBreak::Random),
+            "forwards" => Ok(TieBreak::Forwards),
+            "backwards" => Ok(TieBreak::Backwards),
+            _ => Err(format_err!("Unknown tie-break rule {:?}.", s)),
+        }
+    }
+}
+
+impl FromStr for BallotPolicy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s {
+            "spoil" => Ok(BallotPolicy::Spoil),
+            "truncate" => Ok(BallotPolicy::Truncate),
+            "strict" => Ok(BallotPolicy::Strict),
+            _ => Err(format_err!("Unknown ballot policy {:?}.", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder() {
+        let config = ElectionConfig::new(2)
+            .method(Method::Gregory)
+            .quota(Quota::Hare)
+            .tie_break(TieBreak::Forwards)
+            .seed(7)
+            .ballot_policy(BallotPolicy::Strict)
+            .precision(4);
+
+        assert_eq!(2, config.seats);
+        assert_eq!(Method::Gregory, config.method);
+        assert_eq!(Quota::Hare, config.quota);
+        assert_eq!(TieBreak::Forwards, config.tie_break);
+        assert_eq!(Some(7), config.seed);
+        assert_eq!(BallotPolicy::Strict, config.ballot_policy);
+        assert_eq!(10_000, config.scale());
+    }
+
+    #[test]
+    fn test_toml_defaults() {
+        let config = ElectionConfig::from_toml("seats = 3\nmethod = \"gregory\"").unwrap();
+
+        assert_eq!(ElectionConfig::new(3).method(Method::Gregory), config);
+    }
+
+    #[test]
+    fn test_rules_without_seats() {
+        let toml = "method = \"gregory\"";
+        assert_eq!(
+            ElectionConfig::new(2).method(Method::Gregory),
+            ElectionConfig::from_toml_with_seats(toml, Some(2)).unwrap()
+        );
+        assert!(ElectionConfig::from_toml_with_seats(toml, None).is_err());
+        assert_eq!(
+            ElectionConfig::new(3),
+            ElectionConfig::from_toml_with_seats("seats = 3", Some(2)).unwrap()
+        );
+
+        let json = r#"{ "quota": "hare" }"#;
+        assert_eq!(
+            ElectionConfig::new(4).quota(Quota::Hare),
+            ElectionConfig::from_json_with_seats(json, Some(4)).unwrap()
+        );
+        assert!(ElectionConfig::from_json_with_seats(json, None).is_err());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let config = ElectionConfig::new(4)
+            .tie_break(TieBreak::Backwards)
+            .seed(1234);
+
+        assert_eq!(
+            config,
+            ElectionConfig::from_toml(&config.to_toml().unwrap()).unwrap()
+        );
+        assert_eq!(
+            config,
+            ElectionConfig::from_json(&config.to_json().unwrap()).unwrap()
+        );
+    }
+}
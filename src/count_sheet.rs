@@ -0,0 +1,473 @@
+/* good_stv - a good single transferable vote utility.
+ * Copyright (C) 2019 good_stv authors
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>. */
+
+//! The count sheet used by returning officers, with a row per candidate and columns for each
+//! stage of the count.
+
+use std::collections::HashMap;
+use std::io::Write;
+
+use failure::*;
+
+use crate::labels::fill;
+use crate::{Counter, ElectionResults, Labels};
+
+/// A grid of the votes held by each candidate throughout the count.
+///
+/// The first column holds the first preferences. Each stage then adds two columns: the votes
+/// transferred during the stage, and everybody's total at the end of it. Below the candidates
+/// are rows for non-transferable votes and the total of every column.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CountSheet {
+    headings: Vec<String>,
+    rows: Vec<CountSheetRow>,
+    elected: Vec<String>,
+    quota: f64,
+    precision: u32,
+    labels: Labels,
+}
+
+/// A row of a [`CountSheet`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct CountSheetRow {
+    label: String,
+    cells: Vec<Option<f64>>,
+    outcome: Option<String>,
+    elected: bool,
+}
+
+impl CountSheet {
+    /// Lay out the `results` of counting with `counter`.
+    pub fn new(counter: &Counter, results: &ElectionResults) -> Self {
+        CountSheet::with_labels(counter, results, Labels::default())
+    }
+
+    /// Lay out the `results` of counting with `counter`, using `labels` for the headings.
+    pub fn with_labels(counter: &Counter, results: &ElectionResults, labels: Labels) -> Self {
+        let ballot_box = counter.ballot_box();
+        let candidates = ballot_box.candidates();
+        let index: HashMap<&str, usize> = candidates
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (name.as_str(), i))
+            .collect();
+
+        let mut totals = vec![0.0; candidates.len()];
+        if let Some(first) = results.rounds().first() {
+            for (name, votes) in first.tallies() {
+                totals[index[name.as_str()]] = *votes;
+            }
+        }
+        let mut exhausted = 0.0;
+        let mut columns = vec![totals.clone()];
+        let mut exhausted_columns = vec![Some(0.0)];
+        let mut headings = vec![labels.first_preferences.clone()];
+        let mut outcomes = vec![None; candidates.len()];
+        let mut elected = Vec::new();
+
+        for round in results.rounds() {
+            let mut change = vec![0.0; candidates.len()];
+            for transfer in round.transfers() {
+                change[index[transfer.source().as_str()]] -= transfer.votes();
+                if let Some(destination) = transfer.destination() {
+                    change[index[destination.as_str()]] += transfer.votes();
+                }
+            }
+            for (total, change) in totals.iter_mut().zip(&change) {
+                *total += change;
+            }
+            exhausted += round.exhausted();
+
+            let stage = round.number();
+            let heading = match round.excluded() {
+                Some(excluded) => fill(&labels.exclusion, stage, ballot_box.display_name(excluded)),
+                None => {
+                    let names: Vec<&str> = round
+                        .elected()
+                        .iter()
+                        .map(|name| ballot_box.display_name(name))
+                        .collect();
+                    fill(&labels.surplus, stage, &names.join(", "))
+                }
+            };
+            for name in round.elected() {
+                outcomes[index[name.as_str()]] = Some((
+                    fill(&labels.elected_at, stage, ballot_box.display_name(name)),
+                    true,
+                ));
+                elected.push(ballot_box.display_name(name).to_owned());
+            }
+            if let Some(name) = round.excluded() {
+                outcomes[index[name.as_str()]] = Some((
+                    fill(&labels.excluded_at, stage, ballot_box.display_name(name)),
+                    false,
+                ));
+            }
+
+            headings.push(heading);
+            headings.push(fill(&labels.stage_total, stage, ""));
+            columns.push(change);
+            columns.push(totals.clone());
+            exhausted_columns.push(Some(round.exhausted()));
+            exhausted_columns.push(Some(exhausted));
+        }
+
+        let mut rows: Vec<CountSheetRow> = candidates
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let (outcome, elected) = match outcomes[i].take() {
+                    Some((outcome, elected)) => (Some(outcome), elected),
+                    None => (None, false),
+                };
+                CountSheetRow {
+                    label: ballot_box.display_name(name).to_owned(),
+                    cells: columns
+                        .iter()
+                        .enumerate()
+                        .map(|(column, values)| transfer_cell(column, values[i]))
+                        .collect(),
+                    outcome,
+                    elected,
+                }
+            })
+            .collect();
+        rows.push(CountSheetRow {
+            label: labels.non_transferable.clone(),
+            cells: exhausted_columns
+                .into_iter()
+                .enumerate()
+                .map(|(column, value)| transfer_cell(column, value.unwrap_or_default()))
+                .collect(),
+            outcome: None,
+            elected: false,
+        });
+        let total_row = CountSheetRow {
+            label: labels.total.clone(),
+            cells: (0..headings.len())
+                .map(|column| match column % 2 {
+                    // The transfers in a stage only move votes around, so they always add to zero.
+                    1 => None,
+                    _ => Some(rows.iter().filter_map(|row| row.cells[column]).sum()),
+                })
+                .collect(),
+            outcome: None,
+            elected: false,
+        };
+        rows.push(total_row);
+
+        CountSheet {
+            headings,
+            rows,
+            elected,
+            quota: counter.quota(),
+            precision: counter.config().precision,
+            labels,
+        }
+    }
+
+    /// The headings of the columns of votes.
+    pub fn headings(&self) -> &[String] {
+        &self.headings
+    }
+
+    /// The display names of the candidates who were elected, in the order they were elected.
+    pub fn elected(&self) -> &[String] {
+        &self.elected
+    }
+
+    /// The rows of the sheet: one per candidate, then non-transferable votes and the total.
+    pub fn rows(&self) -> &[CountSheetRow] {
+        &self.rows
+    }
+
+    /// Format a number of votes as it is written in the sheet, rounded to the precision of the
+    /// count and without trailing zeros.
+    pub fn format_votes(&self, votes: f64) -> String {
+        format_votes(votes, self.precision)
+    }
+
+    /// Write the sheet as CSV, which can be opened by any spreadsheet program. Blank cells are
+    /// stages in which a candidate's votes didn't change.
+    pub fn write_csv<W: Write>(&self, writer: W) -> Result<(), Error> {
+        let mut csv_writer = csv::Writer::from_writer(writer);
+        let mut header = vec![self.labels.candidate.as_str()];
+        header.extend(self.headings.iter().map(String::as_str));
+        header.push(&self.labels.result);
+        csv_writer
+            .write_record(&header)
+            .context("Error writing count sheet.")?;
+        for row in &self.rows {
+            let mut record = vec![row.label.clone()];
+            record.extend(row.cells.iter().map(|cell| {
+                cell.map(|votes| self.format_votes(votes))
+                    .unwrap_or_default()
+            }));
+            record.push(row.outcome.clone().unwrap_or_default());
+            csv_writer
+                .write_record(&record)
+                .context("Error writing count sheet.")?;
+        }
+        csv_writer.flush().context("Error writing count sheet.")?;
+        Ok(())
+    }
+
+    /// Write who was elected, the quota and the sheet as a table of fixed-width columns, for
+    /// reading in a terminal or pasting into an email.
+    pub fn write_text<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        let table = self.table();
+        let widths: Vec<usize> = (0..table[0].len())
+            .map(|column| {
+                table
+                    .iter()
+                    .map(|row| row[column].chars().count())
+                    .max()
+                    .unwrap_or_default()
+            })
+            .collect();
+        let last = widths.len() - 1;
+
+        let mut text = self.summary("", "");
+        for (i, row) in table.iter().enumerate() {
+            let mut line = String::new();
+            for (column, cell) in row.iter().enumerate() {
+                let padding = " ".repeat(widths[column] - cell.chars().count());
+                if column > 0 {
+                    line.push_str("  ");
+                }
+                // Names and results line up on the left, and numbers on the right.
+                if column == 0 || column == last || i == 0 {
+                    line.push_str(cell);
+                    line.push_str(&padding);
+                } else {
+                    line.push_str(&padding);
+                    line.push_str(cell);
+                }
+            }
+            text.push_str(line.trim_end());
+            text.push('\n');
+            if i == 0 {
+                let rule: Vec<String> = widths.iter().map(|&width| "-".repeat(width)).collect();
+                text.push_str(&rule.join("  "));
+                text.push('\n');
+            }
+        }
+        writer
+            .write_all(text.as_bytes())
+            .context("Error writing count sheet.")?;
+        Ok(())
+    }
+
+    /// Write who was elected, the quota and the sheet as a Markdown table.
+    pub fn write_markdown<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        let table = self.table();
+        let last = table[0].len() - 1;
+        let mut text = self.summary("**", "  ");
+        for (i, row) in table.iter().enumerate() {
+            let cells: Vec<String> = row.iter().map(|cell| cell.replace('|', "\\|")).collect();
+            text.push_str(&format!("| {} |\n", cells.join(" | ")));
+            if i == 0 {
+                let alignments: Vec<&str> = (0..row.len())
+                    .map(|column| match column {
+                        0 => "---",
+                        _ if column == last => "---",
+                        _ => "---:",
+                    })
+                    .collect();
+                text.push_str(&format!("| {} |\n", alignments.join(" | ")));
+            }
+        }
+        writer
+            .write_all(text.as_bytes())
+            .context("Error writing count sheet.")?;
+        Ok(())
+    }
+
+    // Who was elected and the quota, with `emphasis` around the labels and `line_end` at the end
+    // of each line but the last.
+    fn summary(&self, emphasis: &str, line_end: &str) -> String {
+        format!(
+            "{0}{1}:{0} {2}{3}\n{0}{4}:{0} {5}\n\n",
+            emphasis,
+            self.labels.elected,
+            self.elected.join(", "),
+            line_end,
+            self.labels.quota,
+            self.format_votes(self.quota)
+        )
+    }
+
+    // The whole sheet as text, including the headings.
+    fn table(&self) -> Vec<Vec<String>> {
+        let mut header = vec![self.labels.candidate.clone()];
+        header.extend(self.headings.iter().cloned());
+        header.push(self.labels.result.clone());
+        let mut table = vec![header];
+        for row in &self.rows {
+            let mut cells = vec![row.label.clone()];
+            cells.extend(row.cells.iter().map(|cell| {
+                cell.map(|votes| self.format_votes(votes))
+                    .unwrap_or_default()
+            }));
+            cells.push(row.outcome.clone().unwrap_or_default());
+            table.push(cells);
+        }
+        table
+    }
+}
+
+impl CountSheetRow {
+    /// The candidate's display name, or what the row totals.
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// The votes in each column, or `None` where nothing was transferred.
+    pub fn cells(&self) -> &[Option<f64>] {
+        &self.cells
+    }
+
+    /// When the candidate was elected or excluded, if they were.
+    pub fn outcome(&self) -> Option<&str> {
+        self.outcome.as_deref()
+    }
+
+    /// Whether the row is for a candidate who was elected.
+    pub fn is_elected(&self) -> bool {
+        self.elected
+    }
+}
+
+// Rounds `votes` to `precision` decimal places, dropping any trailing zeros.
+pub(crate) fn format_votes(votes: f64, precision: u32) -> String {
+    let formatted = format!("{:.*}", precision as usize, votes);
+    let formatted = if formatted.contains('.') {
+        formatted.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        &formatted
+    };
+    match formatted {
+        "-0" => "0".to_owned(),
+        _ => formatted.to_owned(),
+    }
+}
+
+// Odd columns hold the transfers made in a stage, which are left blank when there weren't any.
+fn transfer_cell(column: usize, votes: f64) -> Option<f64> {
+    if column % 2 == 1 && votes == 0.0 {
+        None
+    } else {
+        Some(votes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Election, ElectionConfig, Method};
+    use std::io::Cursor;
+
+    const TEST_CSV: &str = "a,b,c,d\na,b\na,b\na,c\na\nb,c\nc\nc\nd,c\n";
+
+    fn count_sheet(config: ElectionConfig) -> String {
+        let election = Election::from_reader(Cursor::new(TEST_CSV), config).unwrap();
+        let counter = election.counter();
+        let results = counter.results().unwrap();
+        let mut csv = Vec::new();
+        CountSheet::new(&counter, &results)
+            .write_csv(&mut csv)
+            .unwrap();
+        String::from_utf8(csv).unwrap()
+    }
+
+    #[test]
+    fn test_count_sheet() {
+        let csv = count_sheet(ElectionConfig::new(2).method(Method::Gregory).seed(0));
+
+        assert_eq!(
+            "Candidate,First preferences,Stage 1: surplus of a,Stage 1 total,\
+             Stage 2: exclusion of d,Stage 2 total,Stage 3: surplus of c,Stage 3 total,Result
+a,4,-1,3,,3,,3,Elected at stage 1
+b,1,0.5,1.5,,1.5,,1.5,
+c,2,0.25,2.25,1,3.25,-0.22,3.03,Elected at stage 3
+d,1,,1,-1,0,,0,Excluded at stage 2
+Non-transferable,0,0.25,0.25,,0.25,0.22,0.47,
+Total,8,,8,,8,,8,
+",
+            csv
+        );
+    }
+
+    #[test]
+    fn test_text_tables() {
+        let config = ElectionConfig::new(2).method(Method::Gregory).seed(0);
+        let election = Election::from_reader(Cursor::new(TEST_CSV), config).unwrap();
+        let counter = election.counter();
+        let results = counter.results().unwrap();
+        let labels = Labels {
+            total: "Total | all".to_owned(),
+            ..Labels::default()
+        };
+        let sheet = CountSheet::with_labels(&counter, &results, labels);
+        let mut text = Vec::new();
+        sheet.write_text(&mut text).unwrap();
+        let mut markdown = Vec::new();
+        sheet.write_markdown(&mut markdown).unwrap();
+
+        assert_eq!(
+            "Elected: a, c
+Quota: 3
+
+Candidate         First preferences  Stage 1: surplus of a  Stage 1 total  Stage 2: exclusion of d  Stage 2 total  Stage 3: surplus of c  Stage 3 total  Result
+----------------  -----------------  ---------------------  -------------  -----------------------  -------------  ---------------------  -------------  -------------------
+a                                 4                     -1              3                                       3                                     3  Elected at stage 1
+b                                 1                    0.5            1.5                                     1.5                                   1.5
+c                                 2                   0.25           2.25                        1           3.25                  -0.22           3.03  Elected at stage 3
+d                                 1                                     1                       -1              0                                     0  Excluded at stage 2
+Non-transferable                  0                   0.25           0.25                                    0.25                   0.22           0.47
+Total | all                       8                                     8                                       8                                     8
+",
+            String::from_utf8(text).unwrap()
+        );
+        assert_eq!(
+            "**Elected:** a, c  \n\
+**Quota:** 3
+
+| Candidate | First preferences | Stage 1: surplus of a | Stage 1 total | Stage 2: exclusion of d | Stage 2 total | Stage 3: surplus of c | Stage 3 total | Result |
+| --- | ---: | ---: | ---: | ---: | ---: | ---: | ---: | --- |
+| a | 4 | -1 | 3 |  | 3 |  | 3 | Elected at stage 1 |
+| b | 1 | 0.5 | 1.5 |  | 1.5 |  | 1.5 |  |
+| c | 2 | 0.25 | 2.25 | 1 | 3.25 | -0.22 | 3.03 | Elected at stage 3 |
+| d | 1 |  | 1 | -1 | 0 |  | 0 | Excluded at stage 2 |
+| Non-transferable | 0 | 0.25 | 0.25 |  | 0.25 | 0.22 | 0.47 |  |
+| Total \\| all | 8 |  | 8 |  | 8 |  | 8 |  |
+",
+            String::from_utf8(markdown).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_format_votes() {
+        let config = ElectionConfig::new(2).method(Method::Gregory).seed(0);
+        let election = Election::from_reader(Cursor::new(TEST_CSV), config).unwrap();
+        let counter = election.counter();
+        let sheet = CountSheet::new(&counter, &counter.results().unwrap());
+
+        assert_eq!("3", sheet.format_votes(3.0));
+        assert_eq!("0.3", sheet.format_votes(0.1 + 0.2));
+        assert_eq!("0", sheet.format_votes(-0.0001));
+    }
+}
@@ -0,0 +1,829 @@
+/* good_stv - a good single transferable vote utility.
+ * Copyright (C) 2019 good_stv authors
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>. */
+
+//! Counting the ballots in a [`BallotBox`] under a set of rules.
+
+use std::collections::HashMap;
+use std::iter;
+use std::mem;
+
+use failure::*;
+use log::*;
+use rand::rngs::StdRng;
+use rand::seq::{index, SliceRandom};
+use rand::{FromEntropy, SeedableRng};
+
+use crate::batch;
+use crate::{
+    BallotBox, BallotPolicy, Candidate, CandidateId, ElectionConfig, ElectionError, Method, Quota,
+    Round, TieBreak, Transfer, MAX_PRECISION,
+};
+
+// Votes, in fixed-point units of `1 / ElectionConfig::scale()`, held by each candidate.
+type Tallies = Vec<u64>;
+// Votes moved to each destination, or exhausted, while transferring a single candidate's votes.
+type Destinations = Vec<(Option<CandidateId>, u64)>;
+
+/// Results of the election, including all those elected and eliminated.
+#[derive(Debug, Default, PartialEq)]
+pub struct ElectionResults {
+    elected: HashMap<Candidate, f64>,
+    eliminated: HashMap<Candidate, f64>,
+    rounds: Vec<Round>,
+}
+
+impl ElectionResults {
+    fn from_rounds(rounds: Vec<Round>) -> Self {
+        let mut results = ElectionResults::default();
+        for round in &rounds {
+            for candidate in round.elected() {
+                let votes = round.tally(candidate).unwrap_or_default();
+                results.elected.insert(candidate.clone(), votes);
+            }
+            if let Some(candidate) = round.excluded() {
+                let votes = round.tally(candidate).unwrap_or_default();
+                results.eliminated.insert(candidate.clone(), votes);
+            }
+        }
+        results.rounds = rounds;
+        results
+    }
+
+    /// Map of those elected to the number of votes they received at the time of their win.
+    ///
+    /// Vote counts are only fractional when counting with [`Method::Gregory`].
+    pub fn elected(&self) -> &HashMap<Candidate, f64> {
+        &self.elected
+    }
+
+    /// Map of those eliminated to the number of votes they received at the time of their loss.
+    pub fn eliminated(&self) -> &HashMap<Candidate, f64> {
+        &self.eliminated
+    }
+
+    /// Every stage of the count, in order.
+    pub fn rounds(&self) -> &[Round] {
+        &self.rounds
+    }
+}
+
+/// Counts the ballots in a [`BallotBox`] under an [`ElectionConfig`].
+///
+/// A `Counter` only borrows its ballots, so several counters with different rules can be run over
+/// the same `BallotBox`, and [`Counter::results`] can be called as many times as needed.
+#[derive(Debug)]
+pub struct Counter<'a> {
+    ballot_box: &'a BallotBox,
+    config: ElectionConfig,
+    num_spoiled_votes: u64,
+    total_votes: u64,
+    // Preferences of each valid ballot, after applying the ballot policy, along with the number
+    // of times it was cast.
+    ballots: Vec<(&'a [CandidateId], u64)>,
+}
+
+impl<'a> Counter<'a> {
+    /// Construct a `Counter` for the ballots in `ballot_box`.
+    ///
+    /// Returns an [`ElectionError`] if there are no seats to fill or more seats than candidates,
+    /// or if a ballot is invalid under [`BallotPolicy::Strict`].
+    pub fn new(ballot_box: &'a BallotBox, config: ElectionConfig) -> Result<Self, Error> {
+        Counter::validate(ballot_box, &config)?;
+        let mut counter = Counter {
+            ballot_box,
+            config,
+            num_spoiled_votes: Default::default(),
+            total_votes: Default::default(),
+            ballots: Default::default(),
+        };
+        counter.purge_spoiled_votes()?;
+        info!("{} spoiled votes purged.", counter.num_spoiled_votes);
+
+        Ok(counter)
+    }
+
+    /// Returns the rules the election will be counted with.
+    pub fn config(&self) -> &ElectionConfig {
+        &self.config
+    }
+
+    /// Returns the number of ballots which were discarded as spoiled.
+    pub fn num_spoiled_votes(&self) -> u64 {
+        self.num_spoiled_votes
+    }
+
+    /// Returns the total number of valid votes cast in the election.
+    pub fn total_votes(&self) -> u64 {
+        self.total_votes
+    }
+
+    /// Returns the number of votes a candidate must reach to get a seat.
+    pub fn quota(&self) -> f64 {
+        self.to_votes(self.quota_units())
+    }
+
+    /// Returns whether there are exactly as many candidates as seats, in which case every
+    /// candidate is elected without a count.
+    pub fn is_uncontested(&self) -> bool {
+        self.config.seats == self.candidates().len() as u64
+    }
+
+    /// Count the election, returning an [`ElectionResults`] struct representing the results.
+    pub fn results(&self) -> Result<ElectionResults, Error> {
+        let rounds = self.rounds().collect::<Result<_, _>>()?;
+        Ok(ElectionResults::from_rounds(rounds))
+    }
+
+    /// Count the election one stage at a time.
+    ///
+    /// The returned iterator yields each [`Round`] as it is counted, stopping once every seat is
+    /// filled or after yielding the first error. An uncontested election has a single round in
+    /// which every candidate is elected.
+    pub fn rounds(&self) -> Rounds<'_, 'a> {
+        if self.ballots.is_empty() && !self.is_uncontested() {
+            return Rounds::failed(ElectionError::NoValidBallots.into());
+        }
+        match Count::new(self) {
+            Ok(count) => Rounds {
+                count: Some(count),
+                error: None,
+            },
+            Err(err) => Rounds::failed(err),
+        }
+    }
+
+    fn validate(ballot_box: &BallotBox, config: &ElectionConfig) -> Result<(), Error> {
+        let num_candidates = ballot_box.candidates().len() as u64;
+        if config.seats == 0 {
+            return Err(ElectionError::ZeroSeats.into());
+        }
+        if config.seats > num_candidates {
+            return Err(ElectionError::TooManySeats {
+                seats: config.seats,
+                candidates: num_candidates,
+            }
+            .into());
+        }
+        if config.precision > MAX_PRECISION {
+            return Err(ElectionError::PrecisionTooHigh(config.precision, MAX_PRECISION).into());
+        }
+        Ok(())
+    }
+
+    // A spoiled vote is a vote which is empty or contains a candidate who doesn't exist. What
+    // happens to it depends on the ballot policy.
+    fn purge_spoiled_votes(&mut self) -> Result<(), Error> {
+        let ballot_box = self.ballot_box;
+        for ballot in ballot_box.ballots() {
+            let mut preferences = ballot.preferences();
+            if let Some(i) = preferences
+                .iter()
+                .position(|&id| !ballot_box.is_candidate(id))
+            {
+                let name = &ballot_box.names()[preferences[i]];
+                info!("Candidate voted for but not running: {}.", name);
+                match self.config.ballot_policy {
+                    BallotPolicy::Spoil => preferences = &[],
+                    BallotPolicy::Truncate => preferences = &preferences[..i],
+                    BallotPolicy::Strict => {
+                        return Err(ElectionError::UnknownCandidate(name.clone()).into());
+                    }
+                }
+            }
+            if preferences.is_empty() {
+                if self.config.ballot_policy == BallotPolicy::Strict {
+                    return Err(ElectionError::EmptyBallot.into());
+                }
+                self.num_spoiled_votes += ballot.count();
+                continue;
+            }
+            self.total_votes += ballot.count();
+            self.ballots.push((preferences, ballot.count()));
+        }
+        Ok(())
+    }
+
+    /// Returns the ballots cast in the election.
+    pub fn ballot_box(&self) -> &'a BallotBox {
+        self.ballot_box
+    }
+
+    // The valid ballots, after applying the ballot policy, with the number of times each was cast.
+    pub(crate) fn valid_ballots(&self) -> &[(&'a [CandidateId], u64)] {
+        &self.ballots
+    }
+
+    fn candidates(&self) -> &'a [Candidate] {
+        self.ballot_box.candidates()
+    }
+
+    fn quota_units(&self) -> u64 {
+        let seats = self.config.seats;
+        let scale = self.config.scale();
+        match self.config.quota {
+            Quota::Droop => (self.total_votes() / (seats + 1) + 1) * scale,
+            Quota::Hare => self.total_votes() * scale / seats,
+        }
+    }
+
+    fn to_votes(&self, units: u64) -> f64 {
+        units as f64 / self.config.scale() as f64
+    }
+}
+
+/// Iterator over the stages of a count, returned by [`Counter::rounds`].
+#[derive(Debug)]
+pub struct Rounds<'c, 'a> {
+    count: Option<Count<'c, 'a>>,
+    error: Option<Error>,
+}
+
+impl<'c, 'a> Rounds<'c, 'a> {
+    fn failed(error: Error) -> Self {
+        Rounds {
+            count: None,
+            error: Some(error),
+        }
+    }
+}
+
+impl<'c, 'a> Iterator for Rounds<'c, 'a> {
+    type Item = Result<Round, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(error) = self.error.take() {
+            return Some(Err(error));
+        }
+        let result = self.count.as_mut()?.next_round();
+        match result {
+            Ok(Some(round)) => Some(Ok(round)),
+            Ok(None) => {
+                self.count = None;
+                None
+            }
+            Err(err) => {
+                self.count = None;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Status {
+    Continuing,
+    Elected,
+    Excluded,
+}
+
+// A number of identical ballots, all of the same value, sitting on a candidate's pile.
+#[derive(Clone, Copy, Debug)]
+struct Parcel {
+    // Index into `Counter::ballots`.
+    ballot: usize,
+    // Index of the preference the ballots are currently counting towards.
+    preference: usize,
+    count: u64,
+    // Value of each ballot, in fixed-point units.
+    value: u64,
+}
+
+// The state of a single run of the count, which is thrown away once the results are known.
+#[derive(Debug)]
+struct Count<'c, 'a> {
+    counter: &'c Counter<'a>,
+    piles: Vec<Vec<Parcel>>,
+    tallies: Tallies,
+    status: Vec<Status>,
+    num_elected: usize,
+    // Tallies at each stage, used to break ties.
+    history: Vec<Tallies>,
+    rng: StdRng,
+}
+
+impl<'c, 'a> Count<'c, 'a> {
+    fn new(counter: &'c Counter<'a>) -> Result<Self, Error> {
+        let rng = match counter.config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let num_candidates = counter.candidates().len();
+        let mut count = Count {
+            counter,
+            piles: vec![Vec::new(); num_candidates],
+            tallies: vec![0; num_candidates],
+            status: vec![Status::Continuing; num_candidates],
+            num_elected: 0,
+            history: Vec::new(),
+            rng,
+        };
+
+        // First-choice votes
+        let scale = counter.config.scale();
+        for (ballot, &(preferences, num_ballots)) in counter.ballots.iter().enumerate() {
+            let &first = preferences.first().ok_or(ElectionError::EmptyBallot)?;
+            if !counter.ballot_box.is_candidate(first) {
+                let name = &counter.ballot_box.names()[first];
+                return Err(ElectionError::UnknownCandidate(name.clone()).into());
+            }
+            count.piles[first].push(Parcel {
+                ballot,
+                preference: 0,
+                count: num_ballots,
+                value: scale,
+            });
+            count.tallies[first] += num_ballots * scale;
+        }
+
+        Ok(count)
+    }
+
+    // Counts the next stage, or returns `None` if every seat has been filled.
+    fn next_round(&mut self) -> Result<Option<Round>, Error> {
+        let counter = self.counter;
+        if self.num_elected >= counter.config.seats as usize {
+            return Ok(None);
+        }
+
+        let tallies: Vec<(CandidateId, u64)> =
+            self.continuing().map(|id| (id, self.tallies[id])).collect();
+        self.history.push(self.tallies.clone());
+        let mut transfers = Vec::new();
+        let mut excluded = None;
+
+        let quota = if counter.is_uncontested() {
+            info!("Election is uncontested, electing all candidates.");
+            0
+        } else {
+            counter.quota_units()
+        };
+        let elected_this_round: Vec<CandidateId> = self
+            .continuing()
+            .filter(|&id| self.tallies[id] >= quota)
+            .collect();
+        for &id in &elected_this_round {
+            self.status[id] = Status::Elected;
+            self.num_elected += 1;
+        }
+        if counter.is_uncontested() {
+            // Everybody is elected, so there is nothing left to transfer.
+        } else if !elected_this_round.is_empty() {
+            // If there were winners this round, redistribute their surplus votes.
+            for &winner in &elected_this_round {
+                let destinations = self.distribute_winner_excess(winner)?;
+                transfers.extend(self.to_transfers(winner, destinations));
+            }
+        } else {
+            // If there were no winners this round, choose a loser, eliminate them, and
+            // distribute their votes.
+            let loser = self.get_round_loser()?;
+            self.status[loser] = Status::Excluded;
+            let destinations = self.distribute_loser_votes(loser)?;
+            transfers.extend(self.to_transfers(loser, destinations));
+            excluded = Some(self.name(loser));
+        }
+
+        let round = Round {
+            number: self.history.len() as u64,
+            tallies: tallies
+                .into_iter()
+                .map(|(id, units)| (self.name(id), counter.to_votes(units)))
+                .collect(),
+            elected: elected_this_round
+                .into_iter()
+                .map(|id| self.name(id))
+                .collect(),
+            excluded,
+            transfers,
+        };
+        for event in round.events() {
+            info!("Stage {}: {}.", round.number, event);
+        }
+        Ok(Some(round))
+    }
+
+    fn name(&self, id: CandidateId) -> Candidate {
+        self.counter.candidates()[id].clone()
+    }
+
+    fn is_continuing(&self, id: CandidateId) -> bool {
+        self.status.get(id) == Some(&Status::Continuing)
+    }
+
+    // Continuing candidates, in the order they were listed.
+    fn continuing<'s>(&'s self) -> impl Iterator<Item = CandidateId> + 's {
+        (0..self.status.len()).filter(move |&id| self.is_continuing(id))
+    }
+
+    fn get_round_loser(&mut self) -> Result<CandidateId, Error> {
+        let lowest = self
+            .continuing()
+            .map(|id| self.tallies[id])
+            .min()
+            .ok_or(ElectionError::NotEnoughVotesError)?;
+        let tied: Vec<CandidateId> = self
+            .continuing()
+            .filter(|&id| self.tallies[id] == lowest)
+            .collect();
+        Ok(self.break_tie(tied))
+    }
+
+    fn break_tie(&mut self, mut tied: Vec<CandidateId>) -> CandidateId {
+        let stages: Box<dyn Iterator<Item = &Tallies>> = match self.counter.config.tie_break {
+            TieBreak::Random => Box::new(iter::empty()),
+            TieBreak::Forwards => Box::new(self.history.iter()),
+            TieBreak::Backwards => Box::new(self.history.iter().rev()),
+        };
+        for stage in stages {
+            if tied.len() == 1 {
+                break;
+            }
+            if let Some(lowest) = tied.iter().map(|&id| stage[id]).min() {
+                tied.retain(|&id| stage[id] == lowest);
+            }
+        }
+        if tied.len() > 1 {
+            let names: Vec<Candidate> = tied.iter().map(|&id| self.name(id)).collect();
+            info!("Choosing randomly between tied candidates {:?}.", names);
+        }
+        *tied
+            .choose(&mut self.rng)
+            .expect("at least one candidate is tied")
+    }
+
+    fn distribute_winner_excess(&mut self, candidate: CandidateId) -> Result<Destinations, Error> {
+        // Calculate how many surplus votes to distribute.
+        let total = self.tallies[candidate];
+        let surplus = total - self.counter.quota_units();
+        let pile = mem::take(&mut self.piles[candidate]);
+
+        match self.counter.config.method {
+            Method::Random => {
+                // Choose which of the individual ballots in the pile to transfer, then work out
+                // how many of them came from each parcel.
+                let num_surplus = (surplus / self.counter.config.scale()) as usize;
+                let mut ends = Vec::with_capacity(pile.len());
+                let mut num_ballots = 0;
+                for parcel in &pile {
+                    num_ballots += parcel.count as usize;
+                    ends.push(num_ballots);
+                }
+                let mut chosen = vec![0; pile.len()];
+                for i in index::sample(&mut self.rng, num_ballots, num_surplus).into_iter() {
+                    let parcel = match ends.binary_search(&i) {
+                        Ok(parcel) => parcel + 1,
+                        Err(parcel) => parcel,
+                    };
+                    chosen[parcel] += 1;
+                }
+                let parcels = pile
+                    .into_iter()
+                    .zip(chosen)
+                    .map(|(parcel, count)| (Parcel { count, ..parcel }, parcel.value))
+                    .collect();
+                Ok(self.transfer_parcels(parcels))
+            }
+            Method::Gregory => {
+                // Truncating to whole units rounds the transfer value down to the configured
+                // precision.
+                let parcels = pile
+                    .into_iter()
+                    .map(|parcel| {
+                        let value = u128::from(parcel.value) * u128::from(surplus);
+                        (parcel, (value / u128::from(total)) as u64)
+                    })
+                    .collect();
+                Ok(self.transfer_parcels(parcels))
+            }
+        }
+    }
+
+    fn distribute_loser_votes(&mut self, candidate: CandidateId) -> Result<Destinations, Error> {
+        let pile = mem::take(&mut self.piles[candidate]);
+        let parcels = pile
+            .into_iter()
+            .map(|parcel| (parcel, parcel.value))
+            .collect();
+        Ok(self.transfer_parcels(parcels))
+    }
+
+    // Moves each parcel onto the pile of its highest remaining continuing preference, at the
+    // given value per ballot. Parcels with no remaining preferences are exhausted and dropped.
+    //
+    // Finding the next preferences doesn't change the state of the count, so with the `parallel`
+    // feature it is spread across threads. The parcels are then moved in their original order, so
+    // the piles come out the same either way.
+    fn transfer_parcels(&mut self, parcels: Vec<(Parcel, u64)>) -> Destinations {
+        let ballots = &self.counter.ballots;
+        let status = &self.status;
+        let next_preferences = batch::map(&parcels, |&(parcel, _)| {
+            let (preferences, _) = ballots[parcel.ballot];
+            preferences
+                .iter()
+                .enumerate()
+                .skip(parcel.preference + 1)
+                .find(|&(_, &id)| status.get(id) == Some(&Status::Continuing))
+                .map(|(preference, &id)| (preference, id))
+        });
+
+        let mut destinations = Destinations::new();
+        for ((parcel, value), next) in parcels.into_iter().zip(next_preferences) {
+            if value == 0 || parcel.count == 0 {
+                continue;
+            }
+            let units = parcel.count * value;
+            let destination = next.map(|(preference, id)| {
+                self.piles[id].push(Parcel {
+                    preference,
+                    value,
+                    ..parcel
+                });
+                self.tallies[id] += units;
+                id
+            });
+            match destinations.iter_mut().find(|(d, _)| *d == destination) {
+                Some((_, total)) => *total += units,
+                None => destinations.push((destination, units)),
+            }
+        }
+        destinations
+    }
+
+    // Converts the destinations of a candidate's votes into transfers, with the continuing
+    // candidates in the order they were listed and exhausted votes last.
+    fn to_transfers(&self, source: CandidateId, mut destinations: Destinations) -> Vec<Transfer> {
+        destinations.sort_by_key(|&(destination, _)| destination.unwrap_or(usize::MAX));
+        destinations
+            .into_iter()
+            .map(|(destination, units)| Transfer {
+                source: self.name(source),
+                destination: destination.map(|id| self.name(id)),
+                votes: self.counter.to_votes(units),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RoundEvent;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_quota_calculation() {
+        let candidates = vec!["a".to_owned(), "b".to_owned(), "c".to_owned()];
+        let votes = vec![vec!["a".to_owned()]; 100];
+        let ballot_box = BallotBox::new(candidates, votes).unwrap();
+        let counter = Counter::new(&ballot_box, ElectionConfig::new(2)).unwrap();
+
+        assert_eq!(counter.quota(), 34.0);
+    }
+
+    #[test]
+    fn test_election_results() {
+        let expected_elected = {
+            let mut elected = HashMap::new();
+            elected.insert("a".to_owned(), 4.0);
+            elected.insert("c".to_owned(), 4.0);
+            elected
+        };
+        let expected_eliminated = {
+            let mut eliminated = HashMap::new();
+            eliminated.insert("b".to_owned(), 2.0);
+            eliminated.insert("d".to_owned(), 1.0);
+            eliminated
+        };
+        let test_csv = "a,b,c,d\nc,b,a\nc,b,a\nb,c\na,b\nc,b\nb,a\nc,b,a\nd,a\na,b";
+        let ballot_box = BallotBox::from_reader(Cursor::new(test_csv)).unwrap();
+        let counter = Counter::new(&ballot_box, ElectionConfig::new(2)).unwrap();
+
+        let results = counter.results().unwrap();
+
+        assert_eq!(&expected_elected, results.elected());
+        assert_eq!(&expected_eliminated, results.eliminated());
+        assert_eq!(4, results.rounds().len());
+    }
+
+    #[test]
+    fn test_rounds() {
+        let test_csv = "a,b,c,d\nc,b,a\nc,b,a\nb,c\na,b\nc,b\nb,a\nc,b,a\nd,a\na,b";
+        let ballot_box = BallotBox::from_reader(Cursor::new(test_csv)).unwrap();
+        let counter = Counter::new(&ballot_box, ElectionConfig::new(2)).unwrap();
+        let mut rounds = counter.rounds();
+
+        let first = rounds.next().unwrap().unwrap();
+        assert_eq!(1, first.number());
+        assert_eq!(Some(4.0), first.tally("c"));
+        assert_eq!(&["c".to_owned()], first.elected());
+        assert!(first.transfers().is_empty());
+
+        let second = rounds.next().unwrap().unwrap();
+        assert_eq!(None, second.tally("c"));
+        assert_eq!(Some(&"d".to_owned()), second.excluded());
+        assert_eq!(
+            &[Transfer {
+                source: "d".to_owned(),
+                destination: Some("a".to_owned()),
+                votes: 1.0,
+            }],
+            second.transfers()
+        );
+        assert_eq!(
+            vec![
+                RoundEvent::Excluded {
+                    candidate: &"d".to_owned(),
+                    votes: 1.0,
+                },
+                RoundEvent::Transferred {
+                    source: &"d".to_owned(),
+                    destination: Some(&"a".to_owned()),
+                    votes: 1.0,
+                },
+            ],
+            second.events()
+        );
+        assert_eq!(
+            "1 votes transferred from d to a",
+            second.events()[1].to_string()
+        );
+
+        let third = rounds.next().unwrap().unwrap();
+        assert_eq!(Some(&"b".to_owned()), third.excluded());
+        assert_eq!(1.0, third.exhausted());
+
+        let fourth = rounds.next().unwrap().unwrap();
+        assert_eq!(&["a".to_owned()], fourth.elected());
+        assert!(rounds.next().is_none());
+    }
+
+    #[test]
+    fn test_spoiled_vote_removal() {
+        let expected_results = ElectionResults {
+            elected: {
+                let mut elected = HashMap::new();
+                elected.insert("a".to_owned(), 3.0);
+                elected
+            },
+            ..Default::default()
+        };
+        let test_csv = "a\na\na\nz\na";
+        let ballot_box = BallotBox::from_reader(Cursor::new(test_csv)).unwrap();
+        let counter = Counter::new(&ballot_box, ElectionConfig::new(1)).unwrap();
+        assert_eq!(1, counter.num_spoiled_votes());
+
+        let results = counter.results().unwrap();
+        assert_eq!(expected_results.elected(), results.elected());
+        assert_eq!(expected_results.eliminated(), results.eliminated());
+    }
+
+    #[test]
+    fn test_empty_ballot_is_spoiled() {
+        let candidates = vec!["a".to_owned(), "b".to_owned()];
+        let votes = vec![vec!["a".to_owned()], Vec::new()];
+        let ballot_box = BallotBox::new(candidates, votes).unwrap();
+
+        let counter = Counter::new(&ballot_box, ElectionConfig::new(1)).unwrap();
+
+        assert_eq!(1, counter.num_spoiled_votes());
+    }
+
+    #[test]
+    fn test_empty_ballot_is_an_error_when_strict() {
+        let candidates = vec!["a".to_owned(), "b".to_owned()];
+        let votes = vec![vec!["a".to_owned()], Vec::new()];
+        let ballot_box = BallotBox::new(candidates, votes).unwrap();
+        let config = ElectionConfig::new(1).ballot_policy(BallotPolicy::Strict);
+
+        let err = Counter::new(&ballot_box, config).unwrap_err();
+
+        assert_eq!(
+            Some(&ElectionError::EmptyBallot),
+            err.downcast_ref::<ElectionError>()
+        );
+    }
+
+    #[test]
+    fn test_too_many_seats_is_an_error() {
+        let test_csv = "a,b\na\nb";
+        let ballot_box = BallotBox::from_reader(Cursor::new(test_csv)).unwrap();
+
+        let err = Counter::new(&ballot_box, ElectionConfig::new(3)).unwrap_err();
+
+        assert_eq!(
+            Some(&ElectionError::TooManySeats {
+                seats: 3,
+                candidates: 2
+            }),
+            err.downcast_ref::<ElectionError>()
+        );
+    }
+
+    #[test]
+    fn test_uncontested_election() {
+        let expected_results = ElectionResults {
+            elected: {
+                let mut elected = HashMap::new();
+                elected.insert("a".to_owned(), 2.0);
+                elected.insert("b".to_owned(), 0.0);
+                elected
+            },
+            ..Default::default()
+        };
+        let test_csv = "a,b\na,b\na";
+        let ballot_box = BallotBox::from_reader(Cursor::new(test_csv)).unwrap();
+        let counter = Counter::new(&ballot_box, ElectionConfig::new(2)).unwrap();
+        assert!(counter.is_uncontested());
+
+        let results = counter.results().unwrap();
+
+        assert_eq!(expected_results.elected(), results.elected());
+        assert_eq!(expected_results.eliminated(), results.eliminated());
+    }
+
+    #[test]
+    fn test_truncate_ballot_policy() {
+        let test_csv = "a,b\na,z,b\nz,a\nb";
+        let ballot_box = BallotBox::from_reader(Cursor::new(test_csv)).unwrap();
+        let config = ElectionConfig::new(1).ballot_policy(BallotPolicy::Truncate);
+
+        let counter = Counter::new(&ballot_box, config).unwrap();
+
+        assert_eq!(1, counter.num_spoiled_votes());
+        assert_eq!(counter.ballots, vec![(&[0][..], 1), (&[1][..], 1)]);
+    }
+
+    #[test]
+    fn test_gregory_transfers() {
+        let expected_results = ElectionResults {
+            elected: {
+                let mut elected = HashMap::new();
+                elected.insert("a".to_owned(), 6.0);
+                elected.insert("b".to_owned(), 4.98);
+                elected
+            },
+            ..Default::default()
+        };
+        let test_csv = "a,b,c\na,b\na,b\na,b\na,b\na,b\na,b\nb\nb\nb\nc";
+        let ballot_box = BallotBox::from_reader(Cursor::new(test_csv)).unwrap();
+        let config = ElectionConfig::new(2).method(Method::Gregory);
+        let counter = Counter::new(&ballot_box, config).unwrap();
+
+        let results = counter.results().unwrap();
+
+        assert_eq!(expected_results.elected(), results.elected());
+        assert_eq!(expected_results.eliminated(), results.eliminated());
+    }
+
+    #[test]
+    fn test_backwards_tie_break() {
+        let test_csv = "a,b,c,d\na\na\na\na\nb\nb\nb\nc,a\nc,a\nd,c";
+        let ballot_box = BallotBox::from_reader(Cursor::new(test_csv)).unwrap();
+        for seed in 0..10 {
+            let config = ElectionConfig::new(1)
+                .tie_break(TieBreak::Backwards)
+                .seed(seed);
+            let counter = Counter::new(&ballot_box, config).unwrap();
+
+            let results = counter.results().unwrap();
+
+            assert_eq!(Some(&6.0), results.elected().get("a"));
+            assert_eq!(Some(&3.0), results.eliminated().get("c"));
+            assert_eq!(None, results.eliminated().get("b"));
+        }
+    }
+
+    #[test]
+    fn test_seeded_count_is_repeatable() {
+        let test_csv = "a,b,c\na,b\na,c\na,b\na,c\na,b\nb\nc\nb,c";
+        let ballot_box = BallotBox::from_reader(Cursor::new(test_csv)).unwrap();
+        let counter = Counter::new(&ballot_box, ElectionConfig::new(2).seed(42)).unwrap();
+
+        assert_eq!(counter.results().unwrap(), counter.results().unwrap());
+    }
+
+    #[test]
+    fn test_count_under_several_rules() {
+        let test_csv = "a,b,c\na,b\na,b\na,b\na,b\na,b\na,b\nb\nb\nb\nc";
+        let ballot_box = BallotBox::from_reader(Cursor::new(test_csv)).unwrap();
+        let random = Counter::new(&ballot_box, ElectionConfig::new(2)).unwrap();
+        let gregory =
+            Counter::new(&ballot_box, ElectionConfig::new(2).method(Method::Gregory)).unwrap();
+
+        assert_eq!(Some(&5.0), random.results().unwrap().elected().get("b"));
+        assert_eq!(Some(&4.98), gregory.results().unwrap().elected().get("b"));
+    }
+}
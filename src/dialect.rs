@@ -0,0 +1,258 @@
+/* good_stv - a good single transferable vote utility.
+ * Copyright (C) 2019 good_stv authors
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>. */
+
+//! Options for reading the many variations of CSV produced by spreadsheets.
+
+use std::io::{self, Chain, Cursor, Read};
+
+use csv::{Reader, ReaderBuilder, Trim};
+use failure::*;
+
+use crate::BallotBoxBuilder;
+
+/// How a CSV ballot file is laid out.
+///
+/// The default is plain comma-separated values with `"` for quoting, matching the CSV format
+/// described in the README. Every option can be changed builder-style:
+///
+/// ```
+/// use good_stv::{BallotBox, CsvDialect};
+///
+/// let dialect = CsvDialect::new().delimiter(b';').trim(true).ignore_case(true);
+/// let csv = "Alice ; Bob\nalice; BOB ;\n";
+/// let ballot_box = BallotBox::from_reader_with(csv.as_bytes(), &dialect).unwrap();
+///
+/// assert_eq!(&["Alice", "Bob"], ballot_box.candidates());
+/// assert_eq!(vec![vec!["Alice", "Bob"]], ballot_box.votes().collect::<Vec<_>>());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CsvDialect {
+    delimiter: u8,
+    quote: Option<u8>,
+    comment: Option<u8>,
+    trim: bool,
+    ignore_case: bool,
+    bom: bool,
+}
+
+impl CsvDialect {
+    /// Create the default dialect.
+    pub fn new() -> Self {
+        CsvDialect {
+            delimiter: b',',
+            quote: Some(b'"'),
+            comment: None,
+            trim: false,
+            ignore_case: false,
+            bom: true,
+        }
+    }
+
+    /// Set the character which separates fields. Defaults to `,`.
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Set the character used to quote fields, or `None` to read quotes as part of the field.
+    /// Defaults to `"`.
+    pub fn quote(mut self, quote: Option<u8>) -> Self {
+        self.quote = quote;
+        self
+    }
+
+    /// Set a character which marks a line as a comment when it starts the line. By default there
+    /// are no comments.
+    pub fn comment(mut self, comment: Option<u8>) -> Self {
+        self.comment = comment;
+        self
+    }
+
+    /// Trim whitespace from around names, and skip any cells left empty. Off by default.
+    pub fn trim(mut self, trim: bool) -> Self {
+        self.trim = trim;
+        self
+    }
+
+    /// Match names on ballots to candidates regardless of case. Off by default.
+    pub fn ignore_case(mut self, ignore_case: bool) -> Self {
+        self.ignore_case = ignore_case;
+        self
+    }
+
+    /// Look for a byte order mark, and decode the input as UTF-16 if it has a UTF-16 byte order
+    /// mark. On by default. A UTF-8 byte order mark is always skipped.
+    pub fn bom(mut self, bom: bool) -> Self {
+        self.bom = bom;
+        self
+    }
+
+    pub(crate) fn is_trimmed(&self) -> bool {
+        self.trim
+    }
+
+    // Opens a CSV reader over `reader` using this dialect.
+    pub(crate) fn reader<R: Read>(
+        &self,
+        reader: R,
+        has_headers: bool,
+    ) -> Result<Reader<Input<R>>, Error> {
+        let input = if self.bom {
+            Input::detect(reader)?
+        } else {
+            Input::Utf8(Cursor::new(Vec::new()).chain(reader))
+        };
+        let mut builder = ReaderBuilder::new();
+        builder
+            .has_headers(has_headers)
+            .flexible(true)
+            .delimiter(self.delimiter)
+            .comment(self.comment)
+            .trim(if self.trim { Trim::All } else { Trim::None });
+        match self.quote {
+            Some(quote) => builder.quote(quote),
+            None => builder.quoting(false),
+        };
+        Ok(builder.from_reader(input))
+    }
+
+    // Applies the options which affect how names are matched.
+    pub(crate) fn configure(&self, builder: &mut BallotBoxBuilder) -> Result<(), Error> {
+        builder.set_ignore_case(self.ignore_case)
+    }
+}
+
+impl Default for CsvDialect {
+    fn default() -> Self {
+        CsvDialect::new()
+    }
+}
+
+// Input with any UTF-16 decoded to UTF-8.
+#[derive(Debug)]
+pub(crate) enum Input<R> {
+    // The bytes read while looking for a byte order mark, followed by the rest of the input.
+    Utf8(Chain<Cursor<Vec<u8>>, R>),
+    // UTF-16 input is rare enough, and usually small enough, to decode all at once.
+    Utf16(Cursor<Vec<u8>>),
+}
+
+impl<R: Read> Input<R> {
+    fn detect(mut reader: R) -> Result<Self, Error> {
+        let mut start = [0; 2];
+        let mut len = 0;
+        while len < start.len() {
+            match reader.read(&mut start[len..]) {
+                Ok(0) => break,
+                Ok(n) => len += n,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.context("Error reading CSV.").into()),
+            }
+        }
+        let big_endian = match &start[..len] {
+            [0xFF, 0xFE] => false,
+            [0xFE, 0xFF] => true,
+            _ => {
+                return Ok(Input::Utf8(
+                    Cursor::new(start[..len].to_vec()).chain(reader),
+                ))
+            }
+        };
+
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .context("Error reading CSV.")?;
+        let units: Vec<u16> = bytes
+            .chunks(2)
+            .map(|pair| {
+                let pair = [pair[0], *pair.get(1).unwrap_or(&0)];
+                if big_endian {
+                    u16::from_be_bytes(pair)
+                } else {
+                    u16::from_le_bytes(pair)
+                }
+            })
+            .collect();
+        let text = String::from_utf16(&units).context("CSV is not valid UTF-16.")?;
+        Ok(Input::Utf16(Cursor::new(text.into_bytes())))
+    }
+}
+
+impl<R: Read> Read for Input<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Input::Utf8(reader) => reader.read(buf),
+            Input::Utf16(reader) => reader.read(buf),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BallotBox, ElectionError};
+
+    #[test]
+    fn test_spreadsheet_export() {
+        let test_csv = "# Exported from a spreadsheet\n Alice ;'Bob; Smith';Charlie\n\
+                        alice;'bob; smith' ;;\n# A comment\nCharlie;Alice\n";
+        let dialect = CsvDialect::new()
+            .delimiter(b';')
+            .quote(Some(b'\''))
+            .comment(Some(b'#'))
+            .trim(true)
+            .ignore_case(true);
+
+        let ballot_box = BallotBox::from_reader_with(test_csv.as_bytes(), &dialect).unwrap();
+
+        assert_eq!(&["Alice", "Bob; Smith", "Charlie"], ballot_box.candidates());
+        assert_eq!(
+            vec![vec![0, 1], vec![2, 0]],
+            ballot_box
+                .ballots()
+                .iter()
+                .map(|ballot| ballot.preferences().to_vec())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_utf16() {
+        let mut test_csv = vec![0xFF, 0xFE];
+        for unit in "a\tb\nb\ta\n".encode_utf16() {
+            test_csv.extend_from_slice(&unit.to_le_bytes());
+        }
+        let dialect = CsvDialect::new().delimiter(b'\t');
+
+        let ballot_box = BallotBox::from_reader_with(&test_csv[..], &dialect).unwrap();
+
+        assert_eq!(&["a", "b"], ballot_box.candidates());
+        assert_eq!(vec![vec!["b", "a"]], ballot_box.votes().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_names_differing_by_case_are_duplicates() {
+        let dialect = CsvDialect::new().ignore_case(true);
+
+        let err = BallotBox::from_reader_with("a,A\na\n".as_bytes(), &dialect).unwrap_err();
+
+        assert_eq!(
+            Some(&ElectionError::DuplicateCandidate("A".to_owned())),
+            err.downcast_ref::<ElectionError>()
+        );
+    }
+}
@@ -0,0 +1,115 @@
+/* good_stv - a good single transferable vote utility.
+ * Copyright (C) 2019 good_stv authors
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>. */
+
+//! Every transfer made during a count, as a flat list for charting.
+
+use std::io::Write;
+
+use failure::*;
+use serde_derive::Serialize;
+
+use crate::{Candidate, ElectionResults};
+
+/// Votes moved from one candidate to another, or exhausted, at a stage of the count.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Flow {
+    stage: u64,
+    source: Candidate,
+    destination: Option<Candidate>,
+    votes: f64,
+}
+
+impl Flow {
+    /// The stage of the count in which the votes were transferred.
+    pub fn stage(&self) -> u64 {
+        self.stage
+    }
+
+    /// The elected or excluded candidate the votes came from.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The candidate the votes went to, or `None` if they were exhausted.
+    pub fn destination(&self) -> Option<&str> {
+        self.destination.as_deref()
+    }
+
+    /// The number of votes transferred.
+    pub fn votes(&self) -> f64 {
+        self.votes
+    }
+}
+
+impl ElectionResults {
+    /// Every transfer made during the count, in the order they were made.
+    pub fn flows(&self) -> Vec<Flow> {
+        self.rounds()
+            .iter()
+            .flat_map(|round| {
+                round.transfers().iter().map(move |transfer| Flow {
+                    stage: round.number(),
+                    source: transfer.source().clone(),
+                    destination: transfer.destination().cloned(),
+                    votes: transfer.votes(),
+                })
+            })
+            .collect()
+    }
+
+    /// Write every transfer as CSV with the columns `stage`, `source`, `destination` and `votes`.
+    /// Exhausted votes have an empty destination.
+    pub fn write_flows_csv<W: Write>(&self, writer: W) -> Result<(), Error> {
+        let mut csv_writer = csv::Writer::from_writer(writer);
+        for flow in self.flows() {
+            csv_writer
+                .serialize(flow)
+                .context("Error writing transfers.")?;
+        }
+        csv_writer.flush().context("Error writing transfers.")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Election, ElectionConfig, Method};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_write_flows_csv() {
+        let test_csv = "a,b,c,d\na,b\na,b\na,c\na\nb,c\nc\nc\nd,c\n";
+        let config = ElectionConfig::new(2).method(Method::Gregory).seed(0);
+        let election = Election::from_reader(Cursor::new(test_csv), config).unwrap();
+        let mut csv = Vec::new();
+        election
+            .results()
+            .unwrap()
+            .write_flows_csv(&mut csv)
+            .unwrap();
+
+        assert_eq!(
+            "stage,source,destination,votes
+1,a,b,0.5
+1,a,c,0.25
+1,a,,0.25
+2,d,c,1
+3,c,,0.22
+",
+            String::from_utf8(csv).unwrap()
+        );
+    }
+}
@@ -0,0 +1,226 @@
+/* good_stv - a good single transferable vote utility.
+ * Copyright (C) 2019 good_stv authors
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>. */
+
+//! Writing the results of a count as a self-contained HTML page.
+
+use std::fmt::Write as _;
+use std::io::Write;
+
+use failure::*;
+
+use crate::{CountSheet, Counter, ElectionResults, Report};
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 2em auto; max-width: 60em; padding: 0 1em; color: #222; }
+table { border-collapse: collapse; margin: 1em 0; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.6em; }
+td.votes { text-align: right; font-variant-numeric: tabular-nums; }
+tr.elected td:first-child { font-weight: bold; }
+tr.excluded td { color: #888; }
+tr.summary td { border-top: 2px solid #888; }
+dl { display: grid; grid-template-columns: max-content auto; gap: 0.3em 1em; }
+dt { font-weight: bold; }
+dd { margin: 0; }
+";
+
+impl Counter<'_> {
+    /// Write the `results` of the count as a single HTML page, with a summary of who was elected,
+    /// a table of every stage of the count, where the votes went at each stage, and how many
+    /// ballots were valid.
+    ///
+    /// The page has no scripts and doesn't load anything else, so it can be saved and viewed
+    /// offline.
+    pub fn write_html<W: Write>(
+        &self,
+        results: &ElectionResults,
+        mut writer: W,
+    ) -> Result<(), Error> {
+        let report = Report::new(self, results);
+        let sheet = CountSheet::new(self, results);
+        let mut html = String::new();
+        write_page(&mut html, &report, &sheet, results)?;
+        writer
+            .write_all(html.as_bytes())
+            .context("Error writing HTML report.")?;
+        Ok(())
+    }
+}
+
+fn write_page(
+    html: &mut String,
+    report: &Report,
+    sheet: &CountSheet,
+    results: &ElectionResults,
+) -> Result<(), Error> {
+    let title = escape(report.title().unwrap_or("Election results"));
+    writeln!(html, "<!DOCTYPE html>")?;
+    writeln!(html, "<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">")?;
+    writeln!(html, "<title>{}</title>\n<style>{}</style>", title, STYLE)?;
+    writeln!(html, "</head>\n<body>\n<h1>{}</h1>", title)?;
+
+    writeln!(html, "<h2>Elected</h2>\n<ol>")?;
+    for outcome in report.elected() {
+        writeln!(
+            html,
+            "<li>{} with {} votes at stage {}</li>",
+            escape(display_name(report, outcome.name())),
+            sheet.format_votes(outcome.votes()),
+            outcome.round()
+        )?;
+    }
+    writeln!(html, "</ol>")?;
+
+    let config = report.config();
+    let exhausted: f64 = results.rounds().iter().map(|round| round.exhausted()).sum();
+    writeln!(html, "<h2>Summary</h2>\n<dl>")?;
+    let summary = [
+        ("Seats", report.seats().to_string()),
+        ("Valid ballots", report.total_votes().to_string()),
+        ("Spoiled ballots", report.spoiled_votes().to_string()),
+        ("Quota", sheet.format_votes(report.quota())),
+        ("Non-transferable votes", sheet.format_votes(exhausted)),
+        ("Surplus transfers", format!("{:?}", config.method)),
+        ("Quota formula", format!("{:?}", config.quota)),
+        ("Tie-break", format!("{:?}", config.tie_break)),
+    ];
+    for (term, description) in &summary {
+        writeln!(html, "<dt>{}</dt><dd>{}</dd>", term, escape(description))?;
+    }
+    if !report.withdrawn().is_empty() {
+        let withdrawn: Vec<String> = report.withdrawn().iter().map(|name| escape(name)).collect();
+        writeln!(html, "<dt>Withdrawn</dt><dd>{}</dd>", withdrawn.join(", "))?;
+    }
+    writeln!(html, "</dl>")?;
+
+    writeln!(
+        html,
+        "<h2>Stages</h2>\n<table>\n<thead>\n<tr><th>Candidate</th>"
+    )?;
+    for heading in sheet.headings() {
+        write!(html, "<th>{}</th>", escape(heading))?;
+    }
+    writeln!(html, "<th>Result</th></tr>\n</thead>\n<tbody>")?;
+    let num_candidates = report.candidates().len();
+    for (i, row) in sheet.rows().iter().enumerate() {
+        let class = match row.outcome() {
+            Some(_) if row.is_elected() => " class=\"elected\"",
+            Some(_) => " class=\"excluded\"",
+            None if i >= num_candidates => " class=\"summary\"",
+            None => "",
+        };
+        write!(html, "<tr{}><td>{}</td>", class, escape(row.label()))?;
+        for cell in row.cells() {
+            let votes = cell.map(|votes| sheet.format_votes(votes));
+            write!(
+                html,
+                "<td class=\"votes\">{}</td>",
+                votes.unwrap_or_default()
+            )?;
+        }
+        writeln!(
+            html,
+            "<td>{}</td></tr>",
+            escape(row.outcome().unwrap_or_default())
+        )?;
+    }
+    writeln!(html, "</tbody>\n</table>")?;
+
+    writeln!(html, "<h2>Transfers</h2>")?;
+    for round in results.rounds() {
+        if round.transfers().is_empty() {
+            continue;
+        }
+        writeln!(html, "<h3>Stage {}</h3>", round.number())?;
+        writeln!(
+            html,
+            "<table>\n<thead>\n<tr><th>From</th><th>To</th><th>Votes</th></tr>\n</thead>\n<tbody>"
+        )?;
+        for transfer in round.transfers() {
+            let destination = transfer
+                .destination()
+                .map_or("Non-transferable", |name| display_name(report, name));
+            writeln!(
+                html,
+                "<tr><td>{}</td><td>{}</td><td class=\"votes\">{}</td></tr>",
+                escape(display_name(report, transfer.source())),
+                escape(destination),
+                sheet.format_votes(transfer.votes())
+            )?;
+        }
+        writeln!(html, "</tbody>\n</table>")?;
+    }
+
+    writeln!(html, "</body>\n</html>")?;
+    Ok(())
+}
+
+fn display_name<'r>(report: &'r Report, name: &'r str) -> &'r str {
+    report
+        .candidates()
+        .iter()
+        .find(|candidate| candidate.name() == name)
+        .map_or(name, |candidate| candidate.display_name())
+}
+
+// Escapes text for use in HTML element content or a quoted attribute.
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Election, ElectionConfig};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_write_html() {
+        let test_csv = "a,<b>,c\na,<b>\na,c\na\n<b>\nc,a\n";
+        let election =
+            Election::from_reader(Cursor::new(test_csv), ElectionConfig::new(1).seed(0)).unwrap();
+        let counter = election.counter();
+        let results = counter.results().unwrap();
+        let mut html = Vec::new();
+        counter.write_html(&results, &mut html).unwrap();
+        let html = String::from_utf8(html).unwrap();
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<li>a with 3 votes at stage 1</li>"));
+        assert!(html.contains("<td>&lt;b&gt;</td>"));
+        assert!(!html.contains("<b>"));
+        assert!(!html.contains("src="));
+        assert!(html.ends_with("</html>\n"));
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&#39;s&lt;/a&gt;",
+            escape("<a href=\"x\">Tom & Jerry's</a>")
+        );
+    }
+}
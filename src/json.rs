@@ -0,0 +1,371 @@
+/* good_stv - a good single transferable vote utility.
+ * Copyright (C) 2019 good_stv authors
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>. */
+
+//! Reading ballots from JSON documents and JSON Lines streams.
+//!
+//! A JSON document lists the candidates and the ballots. Every field other than `candidates` and
+//! `ballots` is optional, and each ballot is either a list of names or an object giving the
+//! number of identical ballots:
+//!
+//! ```json
+//! {
+//!     "title": "Committee election",
+//!     "seats": 2,
+//!     "candidates": ["alice", "bob", "charlie", "dave"],
+//!     "withdrawn": ["dave"],
+//!     "ballots": [
+//!         ["alice", "charlie"],
+//!         { "preferences": ["bob"], "count": 3 }
+//!     ]
+//! }
+//! ```
+//!
+//! A JSON Lines stream has the same fields apart from `ballots` on its first line, followed by a
+//! ballot on each line after that:
+//!
+//! ```text
+//! {"seats": 2, "candidates": ["alice", "bob", "charlie"]}
+//! ["alice", "charlie"]
+//! {"preferences": ["bob"], "count": 3}
+//! ```
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+
+use failure::*;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{BallotBox, BallotBoxBuilder, Candidate, Vote};
+
+// Everything about the election apart from the ballots.
+#[derive(Debug, Deserialize, Serialize)]
+struct Header {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    seats: Option<u64>,
+    candidates: Vec<Candidate>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    withdrawn: Vec<Candidate>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Document {
+    #[serde(flatten)]
+    header: Header,
+    ballots: Vec<JsonBallot>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+enum JsonBallot {
+    Preferences(Vote),
+    Weighted {
+        preferences: Vote,
+        #[serde(default = "default_count", alias = "weight")]
+        count: u64,
+    },
+}
+
+fn default_count() -> u64 {
+    1
+}
+
+impl JsonBallot {
+    fn count(&self) -> u64 {
+        match self {
+            JsonBallot::Preferences(_) => 1,
+            JsonBallot::Weighted { count, .. } => *count,
+        }
+    }
+}
+
+impl BallotBox {
+    /// Construct a `BallotBox` given a path to a JSON file.
+    pub fn from_json_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let file = File::open(&path)
+            .context(format!("Error opening file {:?}", path.as_ref().display()))?;
+        BallotBox::from_json_reader(BufReader::new(file))
+    }
+
+    /// Construct a `BallotBox` given any implementation of [`std::io::Read`] which contains a
+    /// JSON document listing the candidates and ballots.
+    ///
+    /// Withdrawn candidates are removed from the ballots and listed in
+    /// [`BallotBox::withdrawn`].
+    pub fn from_json_reader<R: Read>(reader: R) -> Result<Self, Error> {
+        let document: Document =
+            serde_json::from_reader(reader).context("Could not parse JSON ballots.")?;
+        let mut builder = Builder::new(document.header)?;
+        for (number, ballot) in document.ballots.into_iter().enumerate() {
+            if ballot.count() == 0 {
+                return Err(format_err!("Ballot {} has a count of 0.", number + 1));
+            }
+            builder.push(ballot);
+        }
+        Ok(builder.build())
+    }
+
+    /// Construct a `BallotBox` given a path to a JSON Lines file.
+    pub fn from_json_lines_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let file = File::open(&path)
+            .context(format!("Error opening file {:?}", path.as_ref().display()))?;
+        BallotBox::from_json_lines_reader(file)
+    }
+
+    /// Construct a `BallotBox` given any implementation of [`std::io::Read`] which contains a
+    /// JSON Lines stream, with the candidates on the first line and a ballot on each line after
+    /// that.
+    ///
+    /// Ballots are added as they are read, so a long stream can be read without holding every
+    /// ballot in memory.
+    pub fn from_json_lines_reader<R: Read>(reader: R) -> Result<Self, Error> {
+        let mut reader = BufReader::new(reader);
+        let mut line = String::new();
+        let mut number = 0;
+        let mut builder: Option<Builder> = None;
+        loop {
+            line.clear();
+            number += 1;
+            let read = reader
+                .read_line(&mut line)
+                .context("Error reading JSON Lines.")?;
+            if read == 0 {
+                break;
+            }
+            if line.trim().is_empty() {
+                continue;
+            }
+            match builder {
+                Some(ref mut builder) => {
+                    let ballot: JsonBallot = serde_json::from_str(&line)
+                        .context(format!("Could not parse ballot on line {}.", number))?;
+                    if ballot.count() == 0 {
+                        return Err(format_err!("Ballot on line {} has a count of 0.", number));
+                    }
+                    builder.push(ballot);
+                }
+                None => {
+                    let header = serde_json::from_str(&line)
+                        .context(format!("Could not parse candidates on line {}.", number))?;
+                    builder = Some(Builder::new(header)?);
+                }
+            }
+        }
+        match builder {
+            Some(builder) => Ok(builder.build()),
+            None => Err(format_err!("JSON Lines stream is empty.")),
+        }
+    }
+}
+
+impl BallotBox {
+    /// Write the ballots as a JSON document, which can be read back with
+    /// [`BallotBox::from_json_reader`]. Identical ballots are written once, with a count.
+    pub fn write_json<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        let document = Document {
+            header: self.json_header(),
+            ballots: self.json_ballots().collect(),
+        };
+        serde_json::to_writer_pretty(&mut writer, &document).context("Error writing JSON.")?;
+        writeln!(writer).context("Error writing JSON.")?;
+        Ok(())
+    }
+
+    /// Write the ballots as JSON Lines, which can be read back with
+    /// [`BallotBox::from_json_lines_reader`].
+    pub fn write_json_lines<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        serde_json::to_writer(&mut writer, &self.json_header()).context("Error writing JSON.")?;
+        writeln!(writer).context("Error writing JSON.")?;
+        for ballot in self.json_ballots() {
+            serde_json::to_writer(&mut writer, &ballot).context("Error writing JSON.")?;
+            writeln!(writer).context("Error writing JSON.")?;
+        }
+        Ok(())
+    }
+
+    fn json_header(&self) -> Header {
+        Header {
+            title: self.title().map(str::to_owned),
+            seats: self.seats(),
+            candidates: self
+                .candidates()
+                .iter()
+                .chain(self.withdrawn())
+                .cloned()
+                .collect(),
+            withdrawn: self.withdrawn().to_vec(),
+        }
+    }
+
+    fn json_ballots(&self) -> impl Iterator<Item = JsonBallot> + '_ {
+        self.ballots().iter().map(move |ballot| {
+            let preferences = ballot
+                .preferences()
+                .iter()
+                .map(|&id| self.names()[id].clone())
+                .collect();
+            match ballot.count() {
+                1 => JsonBallot::Preferences(preferences),
+                count => JsonBallot::Weighted { preferences, count },
+            }
+        })
+    }
+}
+
+// Fills a `BallotBox` from the parsed JSON.
+struct Builder {
+    inner: BallotBoxBuilder,
+}
+
+impl Builder {
+    fn new(header: Header) -> Result<Self, Error> {
+        let withdrawn = &header.withdrawn;
+        let running = header
+            .candidates
+            .into_iter()
+            .filter(|candidate| !withdrawn.contains(candidate))
+            .collect();
+        let mut inner = BallotBoxBuilder::new(running)?;
+        if let Some(seats) = header.seats {
+            inner.set_seats(seats);
+        }
+        if let Some(title) = header.title {
+            inner.set_title(title);
+        }
+        inner.set_withdrawn(header.withdrawn);
+        Ok(Builder { inner })
+    }
+
+    fn push(&mut self, ballot: JsonBallot) {
+        let count = ballot.count();
+        let preferences = match ballot {
+            JsonBallot::Preferences(preferences) => preferences,
+            JsonBallot::Weighted { preferences, .. } => preferences,
+        };
+        self.inner.push_weighted(&preferences, count);
+    }
+
+    fn build(self) -> BallotBox {
+        self.inner.build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn votes(ballot_box: &BallotBox) -> Vec<String> {
+        ballot_box.votes().map(|vote| vote.join(" ")).collect()
+    }
+
+    #[test]
+    fn test_read_json() {
+        let test_json = r#"{
+            "title": "Committee election",
+            "seats": 2,
+            "candidates": ["a", "b", "c", "d"],
+            "withdrawn": ["d"],
+            "ballots": [
+                ["a", "c"],
+                {"preferences": ["b", "d", "a"], "count": 2},
+                {"preferences": ["c"], "weight": 1},
+                {"preferences": ["a", "c"]}
+            ]
+        }"#;
+
+        let ballot_box = BallotBox::from_json_reader(Cursor::new(test_json)).unwrap();
+
+        assert_eq!(&["a", "b", "c"], ballot_box.candidates());
+        assert_eq!(&["d"], ballot_box.withdrawn());
+        assert_eq!(Some(2), ballot_box.seats());
+        assert_eq!(Some("Committee election"), ballot_box.title());
+        assert_eq!(vec!["a c", "a c", "b a", "b a", "c"], votes(&ballot_box));
+    }
+
+    #[test]
+    fn test_read_json_lines() {
+        let test_jsonl = "{\"candidates\": [\"a\", \"b\"]}\n[\"a\"]\n\n[\"b\", \"a\"]\n\
+                          {\"preferences\": [\"a\", \"b\"], \"count\": 3}\n";
+
+        let ballot_box = BallotBox::from_json_lines_reader(Cursor::new(test_jsonl)).unwrap();
+
+        assert_eq!(&["a", "b"], ballot_box.candidates());
+        assert_eq!(None, ballot_box.seats());
+        assert_eq!(5, ballot_box.num_votes());
+        assert_eq!(3, ballot_box.ballots().len());
+    }
+
+    #[test]
+    fn test_json_lines_errors() {
+        let bad_ballot = "{\"candidates\": [\"a\", \"b\"]}\n[\"a\"]\n[\"b\", 1]\n";
+        let err = BallotBox::from_json_lines_reader(Cursor::new(bad_ballot)).unwrap_err();
+        assert_eq!("Could not parse ballot on line 3.", err.to_string());
+
+        let err = BallotBox::from_json_lines_reader(Cursor::new("\n")).unwrap_err();
+        assert_eq!("JSON Lines stream is empty.", err.to_string());
+
+        let zero_count = "{\"candidates\": [\"a\"]}\n{\"preferences\": [\"a\"], \"count\": 0}\n";
+        let err = BallotBox::from_json_lines_reader(Cursor::new(zero_count)).unwrap_err();
+        assert_eq!("Ballot on line 2 has a count of 0.", err.to_string());
+    }
+
+    #[test]
+    fn test_zero_count() {
+        let test_json = r#"{
+            "candidates": ["a", "b"],
+            "ballots": [["a"], {"preferences": ["b"], "weight": 0}]
+        }"#;
+        let err = BallotBox::from_json_reader(Cursor::new(test_json)).unwrap_err();
+        assert_eq!("Ballot 2 has a count of 0.", err.to_string());
+    }
+
+    #[test]
+    fn test_write_json_round_trip() {
+        let ballot_box = BallotBox::from_json_reader(Cursor::new(
+            r#"{
+                "title": "Test",
+                "candidates": ["a", "b", "c"],
+                "withdrawn": ["c"],
+                "ballots": [["a", "b"], ["b"], ["a", "b"], ["z"]]
+            }"#,
+        ))
+        .unwrap();
+        let mut json = Vec::new();
+        ballot_box.write_json(&mut json).unwrap();
+        let mut json_lines = Vec::new();
+        ballot_box.write_json_lines(&mut json_lines).unwrap();
+
+        assert_eq!(
+            "{\"title\":\"Test\",\"candidates\":[\"a\",\"b\",\"c\"],\"withdrawn\":[\"c\"]}
+{\"preferences\":[\"a\",\"b\"],\"count\":2}
+[\"b\"]
+[\"z\"]
+",
+            String::from_utf8(json_lines.clone()).unwrap()
+        );
+        assert_eq!(
+            ballot_box,
+            BallotBox::from_json_reader(Cursor::new(json)).unwrap()
+        );
+        assert_eq!(
+            ballot_box,
+            BallotBox::from_json_lines_reader(Cursor::new(json_lines)).unwrap()
+        );
+    }
+}
@@ -0,0 +1,138 @@
+/* good_stv - a good single transferable vote utility.
+ * Copyright (C) 2019 good_stv authors
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>. */
+
+//! The words used in result sheets, so they can be translated.
+
+use std::fs;
+use std::path::Path;
+
+use failure::*;
+use serde_derive::{Deserialize, Serialize};
+
+/// Headings and descriptions used in a [`CountSheet`](crate::CountSheet) and the tables written
+/// from it.
+///
+/// `{stage}` is replaced with the number of the stage, and `{candidate}` with the names of the
+/// candidates involved. Any label left out of a TOML or JSON file keeps its English default.
+///
+/// ```
+/// use good_stv::Labels;
+///
+/// let labels = Labels::from_toml("total = \"Iomlán\"").unwrap();
+/// assert_eq!("Iomlán", labels.total);
+/// assert_eq!("Candidate", labels.candidate);
+/// ```
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct Labels {
+    /// Heading of the column of candidate names.
+    pub candidate: String,
+    /// Heading of the column of first preferences.
+    pub first_preferences: String,
+    /// Heading of the transfers in a stage where a surplus was distributed.
+    pub surplus: String,
+    /// Heading of the transfers in a stage where a candidate was excluded.
+    pub exclusion: String,
+    /// Heading of the totals after a stage.
+    pub stage_total: String,
+    /// Heading of the column saying when candidates were elected or excluded.
+    pub result: String,
+    /// Result of a candidate elected at `{stage}`.
+    pub elected_at: String,
+    /// Result of a candidate excluded at `{stage}`.
+    pub excluded_at: String,
+    /// Label of the row of votes with no further preferences.
+    pub non_transferable: String,
+    /// Label of the row of totals.
+    pub total: String,
+    /// Heading of the list of elected candidates.
+    pub elected: String,
+    /// Label of the quota.
+    pub quota: String,
+}
+
+impl Default for Labels {
+    fn default() -> Self {
+        Labels {
+            candidate: "Candidate".to_owned(),
+            first_preferences: "First preferences".to_owned(),
+            surplus: "Stage {stage}: surplus of {candidate}".to_owned(),
+            exclusion: "Stage {stage}: exclusion of {candidate}".to_owned(),
+            stage_total: "Stage {stage} total".to_owned(),
+            result: "Result".to_owned(),
+            elected_at: "Elected at stage {stage}".to_owned(),
+            excluded_at: "Excluded at stage {stage}".to_owned(),
+            non_transferable: "Non-transferable".to_owned(),
+            total: "Total".to_owned(),
+            elected: "Elected".to_owned(),
+            quota: "Quota".to_owned(),
+        }
+    }
+}
+
+impl Labels {
+    /// Read labels from a TOML string.
+    pub fn from_toml(toml: &str) -> Result<Self, Error> {
+        Ok(toml::from_str(toml).context("Could not parse labels.")?)
+    }
+
+    /// Read labels from a JSON string.
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        Ok(serde_json::from_str(json).context("Could not parse labels.")?)
+    }
+
+    /// Read labels from a file, which is read as JSON if it ends in `.json` and as TOML
+    /// otherwise.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let contents =
+            fs::read_to_string(path).context(format!("Error opening file {:?}", path.display()))?;
+        if path.extension().and_then(|ext| ext.to_str()) == Some("json") {
+            Labels::from_json(&contents)
+        } else {
+            Labels::from_toml(&contents)
+        }
+    }
+}
+
+// Fills in the `{stage}` and `{candidate}` placeholders of a label.
+pub(crate) fn fill(label: &str, stage: u64, candidate: &str) -> String {
+    label
+        .replace("{stage}", &stage.to_string())
+        .replace("{candidate}", candidate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill() {
+        let labels = Labels::default();
+
+        assert_eq!("Stage 2: surplus of a, b", fill(&labels.surplus, 2, "a, b"));
+        assert_eq!("Excluded at stage 3", fill(&labels.excluded_at, 3, ""));
+    }
+
+    #[test]
+    fn test_partial_labels() {
+        let labels = Labels::from_json(r#"{"candidate": "Kandidat", "total": "Summe"}"#).unwrap();
+
+        assert_eq!("Kandidat", labels.candidate);
+        assert_eq!("Summe", labels.total);
+        assert_eq!(Labels::default().quota, labels.quota);
+    }
+}
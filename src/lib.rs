@@ -29,277 +29,252 @@
     unused_qualifications
 )]
 
-use std::collections::HashMap;
-use std::fs::File;
-use std::io::Read;
+mod analysis;
+mod ballot_box;
+mod batch;
+mod blt;
+mod browser;
+mod candidates;
+mod config;
+mod count_sheet;
+mod counter;
+mod dialect;
+mod flow;
+mod html;
+mod json;
+mod labels;
+mod manifest;
+mod narrative;
+mod ranks;
+mod report;
+mod round;
+mod svg;
+
+use std::io::{Read, Write};
 use std::path::Path;
 
-use csv::ReaderBuilder;
 use failure::*;
-use log::*;
+
+pub use crate::analysis::*;
+pub use crate::ballot_box::*;
+pub use crate::batch::*;
+pub use crate::browser::*;
+pub use crate::candidates::*;
+pub use crate::config::*;
+pub use crate::count_sheet::*;
+pub use crate::counter::*;
+pub use crate::dialect::*;
+pub use crate::flow::*;
+pub use crate::labels::*;
+pub use crate::manifest::*;
+pub use crate::report::*;
+pub use crate::round::*;
 
 type Candidate = String;
-type CandidateVotesPair = (Candidate, Vec<Vote>);
-type CandidateVotesMap = HashMap<Candidate, Vec<Vote>>;
 /// List of candidate names, ordered from first preference to last preference.
 pub type Vote = Vec<String>;
 
 /// Enum for all the errors that might be returned from the election process.
-#[derive(Clone, Copy, Debug, Fail)]
+#[derive(Clone, Debug, Fail, PartialEq)]
 pub enum ElectionError {
     /// Error thrown when there are seats left over without being filled.
     #[fail(display = "There were not enough votes to fill every seat.")]
     NotEnoughVotesError,
+    /// Error thrown when a ballot does not contain any preferences.
+    #[fail(display = "A ballot did not contain any preferences.")]
+    EmptyBallot,
+    /// Error thrown when a ballot contains a preference for a candidate who is not running.
+    #[fail(display = "Candidate {:?} is not running in this election.", _0)]
+    UnknownCandidate(Candidate),
+    /// Error thrown when an election is asked to fill zero seats.
+    #[fail(display = "An election must have at least one seat to fill.")]
+    ZeroSeats,
+    /// Error thrown when there are more seats to fill than there are candidates.
+    #[fail(
+        display = "Cannot fill {} seats with only {} candidates.",
+        seats, candidates
+    )]
+    TooManySeats {
+        /// Number of seats to be filled.
+        seats: u64,
+        /// Number of candidates running.
+        candidates: u64,
+    },
+    /// Error thrown when the same candidate is listed more than once.
+    #[fail(display = "Candidate {:?} is listed more than once.", _0)]
+    DuplicateCandidate(Candidate),
+    /// Error thrown when more decimal places are requested than can be counted accurately.
+    #[fail(
+        display = "Precision must be at most {} decimal places, not {}.",
+        _1, _0
+    )]
+    PrecisionTooHigh(u32, u32),
+    /// Error thrown when there are no valid ballots left to count.
+    #[fail(display = "There were no valid ballots to count.")]
+    NoValidBallots,
+    /// Error thrown when a ballot ranks more than one candidate equally.
+    #[fail(display = "A ballot ranked more than one candidate equally.")]
+    TiedPreferences,
+    /// Error thrown when a ballot contains a rank which isn't a positive whole number.
+    #[fail(display = "{:?} is not a valid rank.", _0)]
+    InvalidRank(String),
 }
 
-/// Results of the election, including all those elected and eliminated.
-#[derive(Debug, Default, PartialEq)]
-pub struct ElectionResults {
-    elected: HashMap<Candidate, u64>,
-    eliminated: HashMap<Candidate, u64>,
-}
-
-impl ElectionResults {
-    /// Map of those elected to the number of votes they received at the time of their win.
-    pub fn elected(&self) -> &HashMap<Candidate, u64> {
-        &self.elected
-    }
-
-    /// Map of those eliminated to the number of votes they received at the time of their loss.
-    pub fn eliminated(&self) -> &HashMap<Candidate, u64> {
-        &self.eliminated
-    }
-}
-
-/// Represents the process of an election.
+/// Represents an election: a [`BallotBox`] together with the [`ElectionConfig`] to count it with.
 ///
-/// `Election` is constructed with a set of candidates and votes, and is consumed when it returns
-/// the results of the election.
+/// `Election` is the simplest way to count a set of ballots once. To count the same ballots under
+/// several sets of rules, construct a [`BallotBox`] and a [`Counter`] for each set of rules
+/// instead.
 #[derive(Debug)]
 pub struct Election {
-    candidates: Vec<Candidate>,
-    elected: CandidateVotesMap,
-    eliminated: CandidateVotesMap,
-    num_spoiled_votes: u64,
-    seats: u64,
-    votes: Vec<Vote>,
+    ballot_box: BallotBox,
+    config: ElectionConfig,
 }
 
 impl Election {
     /// Manually construct an `Election` where the input data is already in memory.
     ///
     /// The more common way to construct an `Election` is with [`Election::from_csv_file`].
-    pub fn new(candidates: Vec<Candidate>, votes: Vec<Vote>, seats: u64) -> Result<Self, Error> {
-        let mut election = Election {
-            candidates,
-            votes,
-            seats,
-            elected: Default::default(),
-            eliminated: Default::default(),
-            num_spoiled_votes: Default::default(),
-        };
-        let num_spoiled_votes = election.purge_spoiled_votes();
-        info!("{} spoiled votes purged.", num_spoiled_votes);
-        election.num_spoiled_votes = num_spoiled_votes;
-
-        Ok(election)
+    ///
+    /// Returns an [`ElectionError`] if there are no seats to fill, more seats than candidates, or
+    /// a candidate is listed more than once, or if a ballot is invalid under
+    /// [`BallotPolicy::Strict`].
+    pub fn new(
+        candidates: Vec<Candidate>,
+        votes: Vec<Vote>,
+        config: ElectionConfig,
+    ) -> Result<Self, Error> {
+        Election::from_ballot_box(BallotBox::new(candidates, votes)?, config)
+    }
+
+    /// Construct an `Election` from an already filled [`BallotBox`].
+    pub fn from_ballot_box(ballot_box: BallotBox, config: ElectionConfig) -> Result<Self, Error> {
+        // Make sure any problems with the ballots or the rules are found up front, rather than
+        // when the results are asked for.
+        Counter::new(&ballot_box, config)?;
+        Ok(Election { ballot_box, config })
     }
 
     /// Construct an `Election` given a path to a CSV file.
     ///
     /// This is the recommended way to use `Election`.
-    pub fn from_csv_file<P: AsRef<Path>>(path: P, seats: u64) -> Result<Self, Error> {
-        let file = File::open(&path).context(format!(
-            "Error opening file {:?}",
-            path.as_ref().to_str().unwrap()
-        ))?;
-        Election::from_reader(file, seats)
+    pub fn from_csv_file<P: AsRef<Path>>(path: P, config: ElectionConfig) -> Result<Self, Error> {
+        Election::from_ballot_box(BallotBox::from_csv_file(path)?, config)
     }
 
     /// Construct an `Election` given any implementation of [`std::io::Read`] which contains CSV
     /// data.
-    pub fn from_reader<R: Read>(reader: R, seats: u64) -> Result<Self, Error> {
-        let mut csv_reader = ReaderBuilder::new()
-            .has_headers(true)
-            .flexible(true)
-            .from_reader(reader);
-        let candidates = csv_reader
-            .headers()
-            .context("Error parsing CSV header.")?
-            .deserialize(None)
-            .context("Error deserializing CSV into Candidates struct.")?;
-
-        let mut votes = Vec::new();
-        for record in csv_reader.deserialize() {
-            let vote: Vote = record.context("Could not deserialize record.")?;
-            votes.push(vote);
-        }
-
-        Election::new(candidates, votes, seats)
+    pub fn from_reader<R: Read>(reader: R, config: ElectionConfig) -> Result<Self, Error> {
+        Election::from_ballot_box(BallotBox::from_reader(reader)?, config)
     }
 
-    /// Returns the total number of votes cast in the election.
-    pub fn total_votes(&self) -> u64 {
-        self.votes.len() as u64
+    /// Construct an `Election` given a path to a CSV file with one column of ranks per candidate.
+    /// See [`BallotBox::from_rank_reader`].
+    pub fn from_rank_csv_file<P: AsRef<Path>>(
+        path: P,
+        config: ElectionConfig,
+    ) -> Result<Self, Error> {
+        let ballot_box = BallotBox::from_rank_csv_file(path, config.ballot_policy)?;
+        Election::from_ballot_box(ballot_box, config)
     }
 
-    /// Returns the number of votes a candidate must reach to get a seat.
-    pub fn quota(&self) -> u64 {
-        (self.total_votes() / (self.seats + 1)) + 1
+    /// Construct an `Election` given any implementation of [`std::io::Read`] which contains CSV
+    /// data with one column of ranks per candidate. See [`BallotBox::from_rank_reader`].
+    pub fn from_rank_reader<R: Read>(reader: R, config: ElectionConfig) -> Result<Self, Error> {
+        let ballot_box = BallotBox::from_rank_reader(reader, config.ballot_policy)?;
+        Election::from_ballot_box(ballot_box, config)
     }
 
-    /// Returns an [`ElectionResults`] struct representing the results of the election.
+    /// Construct an `Election` given a path to a JSON file. See [`BallotBox::from_json_reader`].
     ///
-    /// Note that this method consumes the `Election`.
-    pub fn results(mut self) -> Result<ElectionResults, Error> {
-        let mut candidate_votes = CandidateVotesMap::new();
-        for candidate in &self.candidates {
-            candidate_votes.insert(candidate.clone(), Vec::new());
-        }
-
-        // First-choice votes
-        for vote in &self.votes {
-            let candidate = candidate_votes.get_mut(&vote[0]).unwrap();
-            candidate.push(vote.clone());
-        }
-
-        while self.elected.len() < self.seats as usize {
-            let elected_this_round = self.get_round_winners(&candidate_votes);
-            self.elected.extend(elected_this_round.clone().into_iter());
-            // If there were winners this round, redistribute their surplus votes and remove them
-            // from candidate_votes.
-            if !elected_this_round.is_empty() {
-                for (candidate, votes) in &elected_this_round {
-                    let num_surplus = self.distribute_winner_excess(
-                        &(candidate.clone(), votes.clone()),
-                        &mut candidate_votes,
-                    );
-                    candidate_votes.remove(candidate);
-                    info!("{:?} redistributed from winner surplus", num_surplus);
-                }
-            } else {
-                // If there were no winners this round, choose a loser, eliminate them, and
-                // distribute their votes.
-                let loser = self.get_round_loser(&candidate_votes)?;
-                self.eliminated.insert(loser.0.clone(), loser.1.clone());
-                let num_redistributed_votes =
-                    self.distribute_loser_votes(&loser, &mut candidate_votes);
-                candidate_votes.remove(&loser.0);
-                info!("{:?} redistributed from loser", num_redistributed_votes);
-            }
-        }
-
-        Ok(ElectionResults {
-            elected: self
-                .elected
-                .into_iter()
-                .map(|(k, v): (Candidate, Vec<Vote>)| (k, v.len() as u64))
-                .collect(),
-            eliminated: self
-                .eliminated
-                .into_iter()
-                .map(|(k, v): (Candidate, Vec<Vote>)| (k, v.len() as u64))
-                .collect(),
-        })
+    /// Any number of seats in the input is ignored; see [`BallotBox::seats`].
+    pub fn from_json_file<P: AsRef<Path>>(path: P, config: ElectionConfig) -> Result<Self, Error> {
+        Election::from_ballot_box(BallotBox::from_json_file(path)?, config)
     }
 
-    // A spoiled vote is a vote containing a candidate who doesn't exist.
-    fn purge_spoiled_votes(&mut self) -> u64 {
-        let before_length = self.votes.len();
-        let candidates = self.candidates.as_slice();
-        self.votes.retain(|vote| {
-            for candidate in vote {
-                if !candidates.contains(candidate) {
-                    info!("Candidate voted for but not running: {}.", candidate);
-                    return false;
-                }
-            }
-            true
-        });
-        (before_length - self.votes.len()) as u64
+    /// Construct an `Election` given any implementation of [`std::io::Read`] which contains a
+    /// JSON document. See [`BallotBox::from_json_reader`].
+    ///
+    /// Any number of seats in the input is ignored; see [`BallotBox::seats`].
+    pub fn from_json_reader<R: Read>(reader: R, config: ElectionConfig) -> Result<Self, Error> {
+        Election::from_ballot_box(BallotBox::from_json_reader(reader)?, config)
     }
 
-    fn get_round_winners(
-        &self,
-        candidate_votes: &CandidateVotesMap,
-    ) -> HashMap<Candidate, Vec<Vote>> {
-        let mut elected = HashMap::new();
-        for (candidate, votes) in candidate_votes {
-            if votes.len() >= self.quota() as usize {
-                elected.insert(candidate.clone(), votes.clone());
-            }
-        }
-        elected
+    /// Construct an `Election` given a path to a JSON Lines file. See
+    /// [`BallotBox::from_json_lines_reader`].
+    ///
+    /// Any number of seats in the input is ignored; see [`BallotBox::seats`].
+    pub fn from_json_lines_file<P: AsRef<Path>>(
+        path: P,
+        config: ElectionConfig,
+    ) -> Result<Self, Error> {
+        Election::from_ballot_box(BallotBox::from_json_lines_file(path)?, config)
     }
 
-    fn get_round_loser(
-        &self,
-        candidate_votes: &CandidateVotesMap,
-    ) -> Result<CandidateVotesPair, Error> {
-        let loser = candidate_votes
-            .iter()
-            .min_by(|a, b| a.1.len().cmp(&b.1.len()));
-        loser
-            .map(|(k, v)| (k.clone(), v.clone()))
-            .ok_or_else(|| ElectionError::NotEnoughVotesError.into())
+    /// Construct an `Election` given any implementation of [`std::io::Read`] which contains a
+    /// JSON Lines stream. See [`BallotBox::from_json_lines_reader`].
+    ///
+    /// Any number of seats in the input is ignored; see [`BallotBox::seats`].
+    pub fn from_json_lines_reader<R: Read>(
+        reader: R,
+        config: ElectionConfig,
+    ) -> Result<Self, Error> {
+        Election::from_ballot_box(BallotBox::from_json_lines_reader(reader)?, config)
     }
 
-    fn distribute_winner_excess(
-        &self,
-        candidate: &CandidateVotesPair,
-        candidate_votes: &mut CandidateVotesMap,
-    ) -> u64 {
-        // Calculate how many surplus votes to distribute.
-        let num_surplus = candidate.1.len() - self.quota() as usize;
-        let surplus_votes = rand::seq::IteratorRandom::choose_multiple(
-            candidate.1.clone().into_iter(),
-            &mut rand::thread_rng(),
-            num_surplus,
-        );
+    /// Construct an `Election` given a path to a BLT file.
+    ///
+    /// Any number of seats in the input is ignored; see [`BallotBox::seats`].
+    pub fn from_blt_file<P: AsRef<Path>>(path: P, config: ElectionConfig) -> Result<Self, Error> {
+        Election::from_ballot_box(BallotBox::from_blt_file(path)?, config)
+    }
 
-        for vote in &surplus_votes {
-            if vote.len() == 1 {
-                continue;
-            }
-            let new_vote = self.strip_inactive_candidates(vote);
-            if new_vote.is_empty() {
-                continue;
-            }
-            let cand = candidate_votes.get_mut(&new_vote[0]).unwrap();
-            cand.push(new_vote.to_vec());
-        }
-
-        num_surplus as u64
+    /// Construct an `Election` given any implementation of [`std::io::Read`] which contains BLT
+    /// data.
+    ///
+    /// Any number of seats in the input is ignored; see [`BallotBox::seats`].
+    pub fn from_blt_reader<R: Read>(reader: R, config: ElectionConfig) -> Result<Self, Error> {
+        Election::from_ballot_box(BallotBox::from_blt_reader(reader)?, config)
+    }
+
+    /// Returns the ballots cast in the election.
+    pub fn ballot_box(&self) -> &BallotBox {
+        &self.ballot_box
+    }
+
+    /// Returns the rules the election will be counted with.
+    pub fn config(&self) -> &ElectionConfig {
+        &self.config
     }
 
-    fn distribute_loser_votes(
-        &self,
-        candidate: &CandidateVotesPair,
-        candidate_votes: &mut CandidateVotesMap,
-    ) -> u64 {
-        for vote in &candidate.1 {
-            if vote.len() == 1 {
-                continue;
-            }
-            let new_vote = self.strip_inactive_candidates(vote);
-            if new_vote.is_empty() {
-                continue;
-            }
-            let cand = candidate_votes.get_mut(&new_vote[0]).unwrap();
-            cand.push(new_vote.to_vec());
-        }
-        candidate.1.len() as u64
+    /// Returns a [`Counter`] for the election, which can give details of the count such as the
+    /// quota.
+    pub fn counter(&self) -> Counter<'_> {
+        Counter::new(&self.ballot_box, self.config).expect("election was validated on creation")
     }
 
-    fn vote_candidate_elected_or_eliminated(&self, candidate: &str) -> bool {
-        self.elected.contains_key(candidate) || self.eliminated.contains_key(candidate)
+    /// Write the election as a BLT file. See [`Counter::write_blt`].
+    pub fn write_blt<W: Write>(&self, writer: W) -> Result<(), Error> {
+        self.counter().write_blt(writer)
     }
 
-    fn strip_inactive_candidates(&self, vote: &[String]) -> Vote {
-        vote.iter()
-            .filter(|candidate| !self.vote_candidate_elected_or_eliminated(candidate))
-            .cloned()
-            .collect()
+    /// Returns an [`ElectionResults`] struct representing the results of the election.
+    pub fn results(&self) -> Result<ElectionResults, Error> {
+        self.counter().results()
+    }
+
+    /// Count the election and write the results as an HTML page. See [`Counter::write_html`].
+    pub fn write_html<W: Write>(&self, writer: W) -> Result<(), Error> {
+        let counter = self.counter();
+        let results = counter.results()?;
+        counter.write_html(&results, writer)
+    }
+
+    /// Count the election, returning a [`Report`] which can be saved as JSON.
+    pub fn report(&self) -> Result<Report, Error> {
+        let counter = self.counter();
+        let results = counter.results()?;
+        Ok(Report::new(&counter, &results))
     }
 }
 
@@ -309,78 +284,39 @@ mod tests {
     use std::io::Cursor;
 
     #[test]
-    fn test_read_csv() {
-        let test_csv = "cand1,cand2,cand3\ncand1,cand2";
+    fn test_zero_seats_is_an_error() {
+        let test_csv = "a,b\na\nb";
         let cursor = Cursor::new(test_csv);
 
-        let election = Election::from_reader(cursor, 10).unwrap();
+        let err = Election::from_reader(cursor, ElectionConfig::new(0)).unwrap_err();
 
         assert_eq!(
-            election.candidates,
-            vec!["cand1".to_owned(), "cand2".to_owned(), "cand3".to_owned()]
-        );
-        assert_eq!(
-            election.votes,
-            vec![vec!["cand1".to_owned(), "cand2".to_owned()]]
+            Some(&ElectionError::ZeroSeats),
+            err.downcast_ref::<ElectionError>()
         );
     }
 
-    #[test]
-    fn test_quota_calculation() {
-        let votes = vec![Vote::default(); 100];
-        let election = Election {
-            votes,
-            seats: 2,
-            candidates: Default::default(),
-            elected: Default::default(),
-            eliminated: Default::default(),
-            num_spoiled_votes: Default::default(),
-        };
-
-        assert_eq!(election.quota(), 34);
-    }
-
     #[test]
     fn test_election_results() {
-        let expected_results = ElectionResults {
-            elected: {
-                let mut elected = HashMap::new();
-                elected.insert("a".to_owned(), 4);
-                elected.insert("c".to_owned(), 4);
-                elected
-            },
-            eliminated: {
-                let mut eliminated = HashMap::new();
-                eliminated.insert("b".to_owned(), 2);
-                eliminated.insert("d".to_owned(), 1);
-                eliminated
-            },
-        };
         let test_csv = "a,b,c,d\nc,b,a\nc,b,a\nb,c\na,b\nc,b\nb,a\nc,b,a\nd,a\na,b";
         let cursor = Cursor::new(test_csv);
-        let election = Election::from_reader(cursor, 2).unwrap();
+        let election = Election::from_reader(cursor, ElectionConfig::new(2)).unwrap();
 
         let results = election.results().unwrap();
 
-        assert_eq!(expected_results, results);
+        assert_eq!(Some(&4.0), results.elected().get("a"));
+        assert_eq!(Some(&4.0), results.elected().get("c"));
+        assert_eq!(results, election.results().unwrap());
     }
 
     #[test]
-    fn test_spoiled_vote_removal() {
-        let expected_results = ElectionResults {
-            elected: {
-                let mut elected = HashMap::new();
-                elected.insert("a".to_owned(), 3);
-                elected
-            },
-            ..Default::default()
-        };
-        let test_csv = "a\na\na\nz\na";
-        let cursor = Cursor::new(test_csv);
-        let election = Election::from_reader(cursor, 1).unwrap();
-        assert_eq!(1, election.num_spoiled_votes);
+    fn test_config_seats_beat_file_seats() {
+        let json = r#"{"seats": 2, "candidates": ["a", "b", "c"], "ballots": [["a"], ["b"]]}"#;
 
-        let results = election.results().unwrap();
-        assert_eq!(expected_results, results);
+        let election =
+            Election::from_json_reader(Cursor::new(json), ElectionConfig::new(1)).unwrap();
+
+        assert_eq!(Some(2), election.ballot_box().seats());
+        assert_eq!(1, election.config().seats);
     }
 }
@@ -0,0 +1,411 @@
+/* good_stv - a good single transferable vote utility.
+ * Copyright (C) 2019 good_stv authors
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>. */
+
+//! Several elections counted together, such as every position at an AGM.
+//!
+//! A manifest lists each position with its ballots, seats and rules:
+//!
+//! ```toml
+//! title = "AGM 2019"
+//!
+//! [[positions]]
+//! name = "Chair"
+//! ballots = "chair.csv"
+//! seats = 1
+//!
+//! [[positions]]
+//! name = "Committee"
+//! ballots = "agm.csv"
+//! columns = ["Committee [Alice]", "Committee [Bob]", "Committee [Carol]"]
+//! seats = 2
+//! method = "gregory"
+//! ```
+
+use std::fs;
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+
+use failure::*;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    count_elections, BallotBox, CsvDialect, Election, ElectionConfig, ElectionResults, Report,
+    REPORT_FORMAT_VERSION,
+};
+
+/// Every position to be elected from one set of ballot exports, read from a TOML or JSON file.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Manifest {
+    #[serde(default)]
+    title: Option<String>,
+    positions: Vec<Position>,
+    // Directory that ballot files are found relative to.
+    #[serde(skip)]
+    base: PathBuf,
+}
+
+/// A single position in a [`Manifest`].
+///
+/// Besides `name` and `ballots`, a position takes every field of an [`ElectionConfig`], of which
+/// only `seats` is required.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Position {
+    name: String,
+    ballots: PathBuf,
+    #[serde(default)]
+    format: Option<BallotFormat>,
+    #[serde(default)]
+    columns: Vec<String>,
+    #[serde(flatten)]
+    config: ElectionConfig,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum BallotFormat {
+    Csv,
+    Ranks,
+    Blt,
+    Json,
+    Jsonl,
+}
+
+impl Position {
+    /// The name of the position.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The file holding the ballots for this position, as given in the manifest.
+    pub fn ballots(&self) -> &Path {
+        &self.ballots
+    }
+
+    /// The columns of a ranks export which belong to this position, or an empty list if the
+    /// whole file does. Columns named like `Committee [Alice]` give the candidate `Alice`.
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    /// The rules the position is counted under.
+    pub fn config(&self) -> &ElectionConfig {
+        &self.config
+    }
+
+    fn read_ballot_box(&self, base: &Path) -> Result<BallotBox, Error> {
+        let path = base.join(&self.ballots);
+        let format = self.format.unwrap_or_else(|| {
+            let extension = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .map(str::to_lowercase);
+            match extension.as_deref() {
+                _ if !self.columns.is_empty() => BallotFormat::Ranks,
+                Some("blt") => BallotFormat::Blt,
+                Some("json") => BallotFormat::Json,
+                Some("jsonl") | Some("ndjson") => BallotFormat::Jsonl,
+                _ => BallotFormat::Csv,
+            }
+        });
+        if !self.columns.is_empty() && format != BallotFormat::Ranks {
+            return Err(format_err!("columns can only be used with ranks files."));
+        }
+        match format {
+            BallotFormat::Csv => BallotBox::from_csv_file(&path),
+            BallotFormat::Blt => BallotBox::from_blt_file(&path),
+            BallotFormat::Json => BallotBox::from_json_file(&path),
+            BallotFormat::Jsonl => BallotBox::from_json_lines_file(&path),
+            BallotFormat::Ranks if self.columns.is_empty() => {
+                BallotBox::from_rank_csv_file(&path, self.config.ballot_policy)
+            }
+            BallotFormat::Ranks => {
+                let csv =
+                    fs::read(&path).context(format!("Error opening file {:?}", path.display()))?;
+                let selected = select_columns(&csv, &self.columns)?;
+                BallotBox::from_rank_reader(Cursor::new(selected), self.config.ballot_policy)
+            }
+        }
+    }
+}
+
+impl Manifest {
+    /// Read a manifest from a TOML string. Ballot files are found relative to the working
+    /// directory.
+    pub fn from_toml(toml: &str) -> Result<Self, Error> {
+        Ok(toml::from_str(toml).context("Could not parse manifest.")?)
+    }
+
+    /// Read a manifest from a JSON string. Ballot files are found relative to the working
+    /// directory.
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        Ok(serde_json::from_str(json).context("Could not parse manifest.")?)
+    }
+
+    /// Read a manifest from a file, which is read as JSON if it ends in `.json` and as TOML
+    /// otherwise. Ballot files are found relative to the manifest.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let contents =
+            fs::read_to_string(path).context(format!("Error opening file {:?}", path.display()))?;
+        let mut manifest = if path.extension().and_then(|ext| ext.to_str()) == Some("json") {
+            Manifest::from_json(&contents)?
+        } else {
+            Manifest::from_toml(&contents)?
+        };
+        manifest.base = path.parent().map(Path::to_owned).unwrap_or_default();
+        Ok(manifest)
+    }
+
+    /// The title of the whole set of elections, if one was given.
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    /// Every position, in the order they were listed.
+    pub fn positions(&self) -> &[Position] {
+        &self.positions
+    }
+
+    /// Read the ballots for every position, returning an election for each in the same order.
+    pub fn elections(&self) -> Result<Vec<Election>, Error> {
+        self.positions
+            .iter()
+            .map(|position| {
+                let ballot_box = position
+                    .read_ballot_box(&self.base)
+                    .context(format!("Error reading ballots for {}.", position.name))?;
+                Ok(Election::from_ballot_box(ballot_box, position.config)
+                    .context(format!("Error in election for {}.", position.name))?)
+            })
+            .collect()
+    }
+
+    /// Count every one of `elections`, as returned by [`Manifest::elections`], returning the
+    /// results in the same order. With the `parallel` feature enabled the positions are counted
+    /// at the same time.
+    pub fn count(&self, elections: &[Election]) -> Result<Vec<ElectionResults>, Error> {
+        count_elections(elections)
+            .into_iter()
+            .zip(&self.positions)
+            .map(|(results, position)| {
+                Ok(results.context(format!("Error counting election for {}.", position.name))?)
+            })
+            .collect()
+    }
+}
+
+// Copies the named columns of a CSV file, in the given order, into a new CSV file.
+fn select_columns(csv: &[u8], columns: &[String]) -> Result<Vec<u8>, Error> {
+    let mut reader = CsvDialect::default().reader(csv, true)?;
+    let headers = reader
+        .headers()
+        .context("Error parsing CSV header.")?
+        .clone();
+    let indices = columns
+        .iter()
+        .map(|column| {
+            headers
+                .iter()
+                .position(|header| header.trim() == column.trim())
+                .ok_or_else(|| format_err!("There is no column called {:?}.", column))
+        })
+        .collect::<Result<Vec<usize>, Error>>()?;
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer
+        .write_record(indices.iter().map(|&i| option_name(&headers[i])))
+        .context("Error writing CSV.")?;
+    for record in reader.records() {
+        let record = record.context("Could not deserialize record.")?;
+        writer
+            .write_record(indices.iter().map(|&i| record.get(i).unwrap_or_default()))
+            .context("Error writing CSV.")?;
+    }
+    writer
+        .into_inner()
+        .map_err(|error| format_err!("Error writing CSV: {}", error))
+}
+
+// Survey tools name the columns of a ranking grid `Question [Option]`, so the candidate is the
+// part in brackets. Other column names are kept whole.
+fn option_name(header: &str) -> &str {
+    let header = header.trim();
+    match (header.rfind(" ["), header.ends_with(']')) {
+        (Some(start), true) => &header[start + 2..header.len() - 1],
+        _ => header,
+    }
+}
+
+/// The results of every position in a [`Manifest`], which can be written out as a single JSON
+/// document.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct CombinedReport {
+    format_version: u32,
+    title: Option<String>,
+    positions: Vec<PositionReport>,
+}
+
+/// The results of a single position in a [`CombinedReport`].
+#[derive(Debug, PartialEq, Serialize)]
+pub struct PositionReport {
+    position: String,
+    #[serde(flatten)]
+    report: Report,
+}
+
+impl PositionReport {
+    /// The name of the position.
+    pub fn position(&self) -> &str {
+        &self.position
+    }
+
+    /// The results of the position.
+    pub fn report(&self) -> &Report {
+        &self.report
+    }
+}
+
+impl CombinedReport {
+    /// Gather the `results` of counting each of `elections`, as returned by
+    /// [`Manifest::elections`] and [`Manifest::count`].
+    pub fn new(manifest: &Manifest, elections: &[Election], results: &[ElectionResults]) -> Self {
+        CombinedReport {
+            format_version: REPORT_FORMAT_VERSION,
+            title: manifest.title.clone(),
+            positions: manifest
+                .positions
+                .iter()
+                .zip(elections)
+                .zip(results)
+                .map(|((position, election), results)| PositionReport {
+                    position: position.name.clone(),
+                    report: Report::new(&election.counter(), results),
+                })
+                .collect(),
+        }
+    }
+
+    /// The title of the whole set of elections, if one was given.
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    /// The results of each position, in the order they were listed.
+    pub fn positions(&self) -> &[PositionReport] {
+        &self.positions
+    }
+
+    /// Write the combined report as pretty-printed JSON. Each position has the same fields as a
+    /// [`Report`], along with a `position` field naming it.
+    pub fn write_json<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        serde_json::to_writer_pretty(&mut writer, self).context("Error writing report.")?;
+        writeln!(writer).context("Error writing report.")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_columns() {
+        let csv = "Chair [a],Chair [b],Secretary [c],Secretary [d]\n1,2,,1\n,1,1,2\n";
+        let selected = select_columns(
+            csv.as_bytes(),
+            &["Secretary [d]".to_owned(), "Secretary [c]".to_owned()],
+        )
+        .unwrap();
+
+        assert_eq!("d,c\n1,\n2,1\n", String::from_utf8(selected).unwrap());
+        assert!(select_columns(csv.as_bytes(), &["Treasurer".to_owned()]).is_err());
+    }
+
+    #[test]
+    fn test_manifest() {
+        let dir =
+            std::env::temp_dir().join(format!("good_stv_test_manifest_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("chair.csv"), "a,b\na\na\nb\n").unwrap();
+        fs::write(
+            dir.join("agm.csv"),
+            "Committee [c],Committee [d],Committee [e],Committee [f],Other
+1,2,,,1
+1,2,,,
+1,,2,,
+1,,,,2
+,1,2,,
+,,1,,
+,,1,,1
+,,2,1,
+",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("agm.toml"),
+            r#"
+                title = "AGM"
+
+                [[positions]]
+                name = "Chair"
+                ballots = "chair.csv"
+                seats = 1
+                seed = 0
+
+                [[positions]]
+                name = "Committee"
+                ballots = "agm.csv"
+                columns = ["Committee [c]", "Committee [d]", "Committee [e]", "Committee [f]"]
+                seats = 2
+                method = "gregory"
+                seed = 0
+            "#,
+        )
+        .unwrap();
+
+        let manifest = Manifest::from_file(dir.join("agm.toml")).unwrap();
+        assert_eq!(Some("AGM"), manifest.title());
+        assert_eq!(2, manifest.positions()[1].config().seats);
+
+        let elections = manifest.elections().unwrap();
+        let results = manifest.count(&elections).unwrap();
+        let report = CombinedReport::new(&manifest, &elections, &results);
+
+        assert_eq!("Chair", report.positions()[0].position());
+        assert_eq!(
+            vec!["a"],
+            report.positions()[0]
+                .report()
+                .elected()
+                .iter()
+                .map(|outcome| outcome.name())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            &["c", "d", "e", "f"],
+            elections[1].ballot_box().candidates()
+        );
+        assert_eq!(8, report.positions()[1].report().total_votes());
+
+        let mut json = Vec::new();
+        report.write_json(&mut json).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!("Committee", json["positions"][1]["position"]);
+        assert_eq!(2, json["positions"][1]["seats"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
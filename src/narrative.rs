@@ -0,0 +1,160 @@
+/* good_stv - a good single transferable vote utility.
+ * Copyright (C) 2019 good_stv authors
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>. */
+
+//! Describing each stage of a count in sentences, for following along as it happens.
+
+use std::io::Write;
+
+use failure::*;
+
+use crate::count_sheet::format_votes;
+use crate::{Counter, Round, RoundEvent};
+
+impl Counter<'_> {
+    /// Describe a stage of the count in a few lines of plain English: who was elected or
+    /// excluded, the size of any surplus, and where the votes went.
+    ///
+    /// The description is built from [`Round::events`]. Any part of a surplus which isn't
+    /// transferred because of rounding is reported as kept by the elected candidate, as it is in the
+    /// [`CountSheet`](crate::CountSheet).
+    pub fn write_narrative<W: Write>(&self, round: &Round, mut writer: W) -> Result<(), Error> {
+        let ballot_box = self.ballot_box();
+        let votes = |votes: f64| {
+            let formatted = format_votes(votes, self.config().precision);
+            if formatted == "1" {
+                "1 vote".to_owned()
+            } else {
+                format!("{} votes", formatted)
+            }
+        };
+
+        writeln!(writer, "Stage {}:", round.number())?;
+        // Transfers grouped by source, with the surplus being transferred if it's this stage's.
+        let mut transfers: Vec<(&str, Vec<String>, f64, Option<f64>)> = Vec::new();
+        for event in round.events() {
+            match event {
+                RoundEvent::Elected {
+                    candidate,
+                    votes: tally,
+                } => {
+                    let surplus = tally - self.quota();
+                    write!(
+                        writer,
+                        "  {} is elected with {}",
+                        ballot_box.display_name(candidate),
+                        votes(tally)
+                    )?;
+                    if surplus > 0.0 && !self.is_uncontested() {
+                        write!(writer, ", a surplus of {}", votes(surplus))?;
+                        transfers.push((candidate, Vec::new(), 0.0, Some(surplus)));
+                    }
+                    writeln!(writer, ".")?;
+                }
+                RoundEvent::Excluded {
+                    candidate,
+                    votes: tally,
+                } => {
+                    writeln!(
+                        writer,
+                        "  {} is excluded with {}.",
+                        ballot_box.display_name(candidate),
+                        votes(tally)
+                    )?;
+                }
+                RoundEvent::Transferred {
+                    source,
+                    destination,
+                    votes: moved,
+                } => {
+                    let moved_votes = format_votes(moved, self.config().precision);
+                    let part = match destination {
+                        Some(name) => {
+                            format!("{} to {}", moved_votes, ballot_box.display_name(name))
+                        }
+                        None => format!("{} non-transferable", moved_votes),
+                    };
+                    match transfers.iter_mut().find(|(name, _, _, _)| name == source) {
+                        Some((_, parts, total, _)) => {
+                            parts.push(part);
+                            *total += moved;
+                        }
+                        None => transfers.push((source, vec![part], moved, None)),
+                    }
+                }
+            }
+        }
+        for (source, mut parts, mut total, surplus) in transfers {
+            if let Some(surplus) = surplus {
+                let kept = format_votes(surplus - total, self.config().precision);
+                if surplus > total && kept != "0" {
+                    parts.push(format!(
+                        "{} kept by {} after rounding",
+                        kept,
+                        ballot_box.display_name(source)
+                    ));
+                    total = surplus;
+                }
+            }
+            if parts.is_empty() {
+                continue;
+            }
+            writeln!(
+                writer,
+                "  {} from {}: {}.",
+                votes(total),
+                ballot_box.display_name(source),
+                parts.join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CountSheet, Election, ElectionConfig, Method};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_write_narrative() {
+        let test_csv = "a,b,c,d\na,b\na,b\na,c\na\nb,c\nc\nc\nd,c\n";
+        let config = ElectionConfig::new(2).method(Method::Gregory).seed(0);
+        let election = Election::from_reader(Cursor::new(test_csv), config).unwrap();
+        let counter = election.counter();
+        let mut narrative = Vec::new();
+        let results = counter.results().unwrap();
+        for round in results.rounds() {
+            counter.write_narrative(round, &mut narrative).unwrap();
+        }
+
+        assert_eq!(
+            "Stage 1:
+  a is elected with 4 votes, a surplus of 1 vote.
+  1 vote from a: 0.5 to b, 0.25 to c, 0.25 non-transferable.
+Stage 2:
+  d is excluded with 1 vote.
+  1 vote from d: 1 to c.
+Stage 3:
+  c is elected with 3.25 votes, a surplus of 0.25 votes.
+  0.25 votes from c: 0.22 non-transferable, 0.03 kept by c after rounding.
+",
+            String::from_utf8(narrative).unwrap()
+        );
+        // The count sheet also leaves the 0.03 with c.
+        let sheet = CountSheet::new(&counter, &results);
+        assert_eq!(Some(3.03), sheet.rows()[2].cells()[6]);
+    }
+}
@@ -0,0 +1,229 @@
+/* good_stv - a good single transferable vote utility.
+ * Copyright (C) 2019 good_stv authors
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>. */
+
+//! Reading ballots laid out with one column per candidate, holding the rank each voter gave them.
+//!
+//! This is how Google Forms and most survey tools export ranked questions:
+//!
+//! ```text
+//! alice,bob,charlie
+//! 2,1,
+//! 1,3,2
+//! ```
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use csv::StringRecord;
+use failure::*;
+
+use crate::{BallotBox, BallotBoxBuilder, BallotPolicy, Candidate, CsvDialect, ElectionError};
+
+impl BallotBox {
+    /// Construct a `BallotBox` given a path to a CSV file with one column of ranks per candidate.
+    /// See [`BallotBox::from_rank_reader`].
+    pub fn from_rank_csv_file<P: AsRef<Path>>(
+        path: P,
+        ballot_policy: BallotPolicy,
+    ) -> Result<Self, Error> {
+        BallotBox::from_rank_csv_file_with(path, ballot_policy, &CsvDialect::default())
+    }
+
+    /// Construct a `BallotBox` given a path to a CSV file in the given dialect, with one column of
+    /// ranks per candidate. See [`BallotBox::from_rank_reader`].
+    pub fn from_rank_csv_file_with<P: AsRef<Path>>(
+        path: P,
+        ballot_policy: BallotPolicy,
+        dialect: &CsvDialect,
+    ) -> Result<Self, Error> {
+        let file = File::open(&path)
+            .context(format!("Error opening file {:?}", path.as_ref().display()))?;
+        BallotBox::from_rank_reader_with(file, ballot_policy, dialect)
+    }
+
+    /// Construct a `BallotBox` given any implementation of [`std::io::Read`] which contains CSV
+    /// data with a header of candidate names, and a row per ballot giving each candidate's rank.
+    ///
+    /// Candidates left blank are unranked, and gaps in the ranks are ignored. Ballots which rank
+    /// candidates equally, or contain a rank which isn't a positive whole number, are handled
+    /// according to `ballot_policy`:
+    ///
+    /// * [`BallotPolicy::Spoil`] discards the whole ballot.
+    /// * [`BallotPolicy::Truncate`] keeps the preferences ranked above the first tie, and ignores
+    ///   invalid ranks.
+    /// * [`BallotPolicy::Strict`] returns an [`ElectionError`].
+    pub fn from_rank_reader<R: Read>(
+        reader: R,
+        ballot_policy: BallotPolicy,
+    ) -> Result<Self, Error> {
+        BallotBox::from_rank_reader_with(reader, ballot_policy, &CsvDialect::default())
+    }
+
+    /// Construct a `BallotBox` given any implementation of [`std::io::Read`] which contains CSV
+    /// data in the given dialect, with one column of ranks per candidate. See
+    /// [`BallotBox::from_rank_reader`].
+    pub fn from_rank_reader_with<R: Read>(
+        reader: R,
+        ballot_policy: BallotPolicy,
+        dialect: &CsvDialect,
+    ) -> Result<Self, Error> {
+        let mut csv_reader = dialect.reader(reader, true)?;
+        let candidates: Vec<Candidate> = csv_reader
+            .headers()
+            .context("Error parsing CSV header.")?
+            .deserialize(None)
+            .context("Error deserializing CSV into Candidates struct.")?;
+
+        let mut builder = BallotBoxBuilder::new(candidates.clone())?;
+        dialect.configure(&mut builder)?;
+        let mut record = StringRecord::new();
+        let mut ranks = Vec::new();
+        while csv_reader
+            .read_record(&mut record)
+            .context("Could not deserialize record.")?
+        {
+            let line = record.position().map_or(0, |position| position.line());
+            // Spreadsheets often leave empty cells past the last column, but a rank there doesn't
+            // belong to any candidate.
+            if record
+                .iter()
+                .skip(candidates.len())
+                .any(|cell| !cell.trim().is_empty())
+            {
+                return Err(format_err!(
+                    "Line {} has more columns than there are candidates.",
+                    line
+                ));
+            }
+            let preferences = ballot_preferences(&record, &mut ranks, ballot_policy)
+                .context(format!("Invalid ballot on line {}.", line))?;
+            builder.push(preferences.iter().map(|&i| &candidates[i]));
+        }
+
+        Ok(builder.build())
+    }
+}
+
+// Works out the order of preferences on a single ballot, as indices into the header. `ranks` is
+// scratch space reused between ballots.
+fn ballot_preferences(
+    record: &StringRecord,
+    ranks: &mut Vec<(u64, usize)>,
+    ballot_policy: BallotPolicy,
+) -> Result<Vec<usize>, ElectionError> {
+    ranks.clear();
+    for (candidate, cell) in record.iter().enumerate() {
+        let cell = cell.trim();
+        if cell.is_empty() {
+            continue;
+        }
+        match cell.parse::<u64>() {
+            Ok(rank) if rank > 0 => ranks.push((rank, candidate)),
+            _ => match ballot_policy {
+                BallotPolicy::Spoil => return Ok(Vec::new()),
+                BallotPolicy::Truncate => continue,
+                BallotPolicy::Strict => return Err(ElectionError::InvalidRank(cell.to_owned())),
+            },
+        }
+    }
+    ranks.sort();
+
+    let mut end = ranks.len();
+    if let Some(tie) = ranks.windows(2).position(|pair| pair[0].0 == pair[1].0) {
+        match ballot_policy {
+            BallotPolicy::Spoil => return Ok(Vec::new()),
+            BallotPolicy::Truncate => end = tie,
+            BallotPolicy::Strict => return Err(ElectionError::TiedPreferences),
+        }
+    }
+    Ok(ranks[..end]
+        .iter()
+        .map(|&(_, candidate)| candidate)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const TEST_CSV: &str = "a,b,c,d\n2,1,,\n1,3,2,4\n,, ,\n1,1,2,\n2,3,3,1\n1,x,2,\n";
+
+    fn votes(ballot_box: &BallotBox) -> Vec<String> {
+        ballot_box.votes().map(|vote| vote.join(" ")).collect()
+    }
+
+    #[test]
+    fn test_read_ranks() {
+        let ballot_box = BallotBox::from_rank_reader(
+            Cursor::new("a,b,c\n2,1,\n1,3,2\n3,5,\n"),
+            BallotPolicy::Spoil,
+        )
+        .unwrap();
+
+        assert_eq!(&["a", "b", "c"], ballot_box.candidates());
+        assert_eq!(vec!["b a", "a c b", "a b"], votes(&ballot_box));
+    }
+
+    #[test]
+    fn test_row_longer_than_header() {
+        let error = BallotBox::from_rank_reader(Cursor::new("a,b\n1,2,3\n"), BallotPolicy::Spoil)
+            .unwrap_err();
+        assert_eq!(
+            "Line 2 has more columns than there are candidates.",
+            error.to_string()
+        );
+
+        let ballot_box =
+            BallotBox::from_rank_reader(Cursor::new("a,b\n2,1,,\n"), BallotPolicy::Spoil).unwrap();
+        assert_eq!(vec!["b a"], votes(&ballot_box));
+    }
+
+    #[test]
+    fn test_spoil_ranks() {
+        let ballot_box =
+            BallotBox::from_rank_reader(Cursor::new(TEST_CSV), BallotPolicy::Spoil).unwrap();
+
+        assert_eq!(vec!["b a", "a c b d", "", "", "", ""], votes(&ballot_box));
+    }
+
+    #[test]
+    fn test_truncate_ranks() {
+        let ballot_box =
+            BallotBox::from_rank_reader(Cursor::new(TEST_CSV), BallotPolicy::Truncate).unwrap();
+
+        assert_eq!(
+            vec!["b a", "a c b d", "", "", "d a", "a c"],
+            votes(&ballot_box)
+        );
+    }
+
+    #[test]
+    fn test_strict_ranks() {
+        let tied = BallotBox::from_rank_reader(Cursor::new("a,b\n1,1\n"), BallotPolicy::Strict)
+            .unwrap_err();
+        let invalid =
+            BallotBox::from_rank_reader(Cursor::new("a,b\n1,\n1,x\n"), BallotPolicy::Strict)
+                .unwrap_err();
+
+        assert_eq!(
+            Some(&ElectionError::TiedPreferences),
+            tied.find_root_cause().downcast_ref::<ElectionError>()
+        );
+        assert_eq!("Invalid ballot on line 3.", invalid.to_string());
+    }
+}
@@ -0,0 +1,319 @@
+/* good_stv - a good single transferable vote utility.
+ * Copyright (C) 2019 good_stv authors
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>. */
+
+//! A summary of a completed count, in a form which can be saved for other programs.
+//!
+//! A [`Report`] serializes to JSON with the following layout. Candidates are always listed in the
+//! order they were given, and new fields may be added in later versions without changing
+//! `format_version`.
+//!
+//! ```json
+//! {
+//!   "format_version": 1,
+//!   "title": "Committee election",
+//!   "seats": 1,
+//!   "quota": 3.0,
+//!   "total_votes": 5,
+//!   "spoiled_votes": 0,
+//!   "candidates": [
+//!     { "name": "alice", "display_name": "Alice Smith", "party": null, "category": null }
+//!   ],
+//!   "withdrawn": [],
+//!   "elected": [{ "name": "alice", "votes": 3.0, "round": 1 }],
+//!   "excluded": [],
+//!   "rounds": [
+//!     {
+//!       "number": 1,
+//!       "tallies": [{ "name": "alice", "votes": 3.0 }],
+//!       "elected": ["alice"],
+//!       "excluded": null,
+//!       "exhausted": 0.0,
+//!       "transfers": []
+//!     }
+//!   ],
+//!   "config": { "seats": 1, "method": "random", "...": "..." }
+//! }
+//! ```
+
+use std::io::Write;
+
+use failure::*;
+use serde_derive::Serialize;
+
+use crate::{Candidate, Counter, ElectionConfig, ElectionResults};
+
+/// The version of the JSON layout written by [`Report`]. It only changes when existing fields are
+/// removed or change meaning.
+pub const REPORT_FORMAT_VERSION: u32 = 1;
+
+/// Everything about a completed count: who was elected and excluded, the tallies at every stage,
+/// and the rules used.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Report {
+    format_version: u32,
+    title: Option<String>,
+    seats: u64,
+    quota: f64,
+    total_votes: u64,
+    spoiled_votes: u64,
+    candidates: Vec<ReportCandidate>,
+    withdrawn: Vec<Candidate>,
+    elected: Vec<Outcome>,
+    excluded: Vec<Outcome>,
+    rounds: Vec<ReportRound>,
+    config: ElectionConfig,
+}
+
+/// A candidate listed in a [`Report`].
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ReportCandidate {
+    name: Candidate,
+    display_name: String,
+    party: Option<String>,
+    category: Option<String>,
+}
+
+/// A candidate who was elected or excluded, with the votes they held at the time.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Outcome {
+    name: Candidate,
+    votes: f64,
+    round: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+struct ReportRound {
+    number: u64,
+    tallies: Vec<Tally>,
+    elected: Vec<Candidate>,
+    excluded: Option<Candidate>,
+    exhausted: f64,
+    transfers: Vec<ReportTransfer>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+struct Tally {
+    name: Candidate,
+    votes: f64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+struct ReportTransfer {
+    from: Candidate,
+    to: Option<Candidate>,
+    votes: f64,
+}
+
+impl Report {
+    /// Summarise the `results` of counting with `counter`.
+    pub fn new(counter: &Counter, results: &ElectionResults) -> Self {
+        let ballot_box = counter.ballot_box();
+        let candidates = ballot_box
+            .candidates()
+            .iter()
+            .map(|name| {
+                let info = ballot_box.candidate_list().and_then(|list| list.get(name));
+                ReportCandidate {
+                    name: name.clone(),
+                    display_name: ballot_box.display_name(name).to_owned(),
+                    party: info.and_then(|info| info.party()).map(str::to_owned),
+                    category: info.and_then(|info| info.category()).map(str::to_owned),
+                }
+            })
+            .collect();
+
+        let mut elected = Vec::new();
+        let mut excluded = Vec::new();
+        for round in results.rounds() {
+            let outcome = |name: &Candidate| Outcome {
+                name: name.clone(),
+                votes: round.tally(name).unwrap_or_default(),
+                round: round.number(),
+            };
+            elected.extend(round.elected().iter().map(outcome));
+            excluded.extend(round.excluded().map(outcome));
+        }
+
+        let rounds = results
+            .rounds()
+            .iter()
+            .map(|round| ReportRound {
+                number: round.number(),
+                tallies: round
+                    .tallies()
+                    .iter()
+                    .map(|(name, votes)| Tally {
+                        name: name.clone(),
+                        votes: *votes,
+                    })
+                    .collect(),
+                elected: round.elected().to_vec(),
+                excluded: round.excluded().cloned(),
+                exhausted: round.exhausted(),
+                transfers: round
+                    .transfers()
+                    .iter()
+                    .map(|transfer| ReportTransfer {
+                        from: transfer.source().clone(),
+                        to: transfer.destination().cloned(),
+                        votes: transfer.votes(),
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        Report {
+            format_version: REPORT_FORMAT_VERSION,
+            title: ballot_box.title().map(str::to_owned),
+            seats: counter.config().seats,
+            quota: counter.quota(),
+            total_votes: counter.total_votes(),
+            spoiled_votes: counter.num_spoiled_votes(),
+            candidates,
+            withdrawn: ballot_box.withdrawn().to_vec(),
+            elected,
+            excluded,
+            rounds,
+            config: *counter.config(),
+        }
+    }
+
+    /// The title of the election, if it has one.
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    /// The number of seats filled.
+    pub fn seats(&self) -> u64 {
+        self.seats
+    }
+
+    /// The number of votes needed to be elected.
+    pub fn quota(&self) -> f64 {
+        self.quota
+    }
+
+    /// The number of valid ballots counted.
+    pub fn total_votes(&self) -> u64 {
+        self.total_votes
+    }
+
+    /// The number of ballots discarded as spoiled.
+    pub fn spoiled_votes(&self) -> u64 {
+        self.spoiled_votes
+    }
+
+    /// The candidates who were running, in the order they were listed.
+    pub fn candidates(&self) -> &[ReportCandidate] {
+        &self.candidates
+    }
+
+    /// The candidates who withdrew before the count.
+    pub fn withdrawn(&self) -> &[Candidate] {
+        &self.withdrawn
+    }
+
+    /// The rules used to count the election.
+    pub fn config(&self) -> &ElectionConfig {
+        &self.config
+    }
+
+    /// The candidates who were elected, in the order they were elected.
+    pub fn elected(&self) -> &[Outcome] {
+        &self.elected
+    }
+
+    /// The candidates who were excluded, in the order they were excluded.
+    pub fn excluded(&self) -> &[Outcome] {
+        &self.excluded
+    }
+
+    /// Write the report as pretty-printed JSON.
+    pub fn write_json<W: Write>(&self, mut writer: W) -> Result<(), Error> {
+        serde_json::to_writer_pretty(&mut writer, self).context("Error writing JSON report.")?;
+        writeln!(writer).context("Error writing JSON report.")?;
+        Ok(())
+    }
+}
+
+impl ReportCandidate {
+    /// The name used for the candidate on ballots.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The name shown for the candidate in reports.
+    pub fn display_name(&self) -> &str {
+        &self.display_name
+    }
+}
+
+impl Outcome {
+    /// The name of the candidate.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The votes the candidate held when they were elected or excluded.
+    pub fn votes(&self) -> f64 {
+        self.votes
+    }
+
+    /// The stage of the count in which the candidate was elected or excluded.
+    pub fn round(&self) -> u64 {
+        self.round
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Election, ElectionConfig};
+    use serde_json::Value;
+    use std::io::Cursor;
+
+    const TEST_CSV: &str = "a,b,c,d\nc,b,a\nc,b,a\nb,c\na,b\nc,b\nb,a\nc,b,a\nd,a\na,b";
+
+    #[test]
+    fn test_report() {
+        let election =
+            Election::from_reader(Cursor::new(TEST_CSV), ElectionConfig::new(2).seed(0)).unwrap();
+        let report = election.report().unwrap();
+
+        assert_eq!(4.0, report.quota());
+        let elected: Vec<_> = report.elected().iter().map(Outcome::name).collect();
+        assert_eq!(vec!["c", "a"], elected);
+        let excluded: Vec<_> = report.excluded().iter().map(Outcome::name).collect();
+        assert_eq!(vec!["d", "b"], excluded);
+        assert_eq!(2, report.excluded()[0].round());
+    }
+
+    #[test]
+    fn test_report_json() {
+        let election =
+            Election::from_reader(Cursor::new(TEST_CSV), ElectionConfig::new(2).seed(0)).unwrap();
+        let mut json = Vec::new();
+        election.report().unwrap().write_json(&mut json).unwrap();
+        let json: Value = serde_json::from_slice(&json).unwrap();
+
+        assert_eq!(REPORT_FORMAT_VERSION, json["format_version"]);
+        assert_eq!(9, json["total_votes"]);
+        assert_eq!("a", json["candidates"][0]["display_name"]);
+        assert_eq!("c", json["elected"][0]["name"]);
+        assert_eq!(4.0, json["rounds"][0]["tallies"][2]["votes"]);
+        assert_eq!(2, json["config"]["seats"]);
+    }
+}
@@ -0,0 +1,182 @@
+/* good_stv - a good single transferable vote utility.
+ * Copyright (C) 2019 good_stv authors
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>. */
+
+//! The stages of a count.
+
+use std::fmt;
+
+use crate::Candidate;
+
+/// A single stage of the count.
+///
+/// In each stage either every candidate who has reached the quota is elected and their surplus
+/// transferred, or the candidate with the fewest votes is excluded and all of their votes
+/// transferred.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Round {
+    pub(crate) number: u64,
+    pub(crate) tallies: Vec<(Candidate, f64)>,
+    pub(crate) elected: Vec<Candidate>,
+    pub(crate) excluded: Option<Candidate>,
+    pub(crate) transfers: Vec<Transfer>,
+}
+
+impl Round {
+    /// The number of this stage, starting from 1.
+    pub fn number(&self) -> u64 {
+        self.number
+    }
+
+    /// Votes held by each continuing candidate at the start of this stage, in the order the
+    /// candidates were listed.
+    pub fn tallies(&self) -> &[(Candidate, f64)] {
+        &self.tallies
+    }
+
+    /// Votes held by `candidate` at the start of this stage, if they were still continuing.
+    pub fn tally(&self, candidate: &str) -> Option<f64> {
+        self.tallies
+            .iter()
+            .find(|(c, _)| c == candidate)
+            .map(|(_, votes)| *votes)
+    }
+
+    /// Candidates elected in this stage.
+    pub fn elected(&self) -> &[Candidate] {
+        &self.elected
+    }
+
+    /// The candidate excluded in this stage, if nobody was elected.
+    pub fn excluded(&self) -> Option<&Candidate> {
+        self.excluded.as_ref()
+    }
+
+    /// Votes moved from elected or excluded candidates during this stage.
+    pub fn transfers(&self) -> &[Transfer] {
+        &self.transfers
+    }
+
+    /// What happened during this stage, in order: who was elected or excluded and with how many
+    /// votes, followed by each transfer made.
+    pub fn events(&self) -> Vec<RoundEvent<'_>> {
+        let elected = self.elected.iter().map(|candidate| RoundEvent::Elected {
+            candidate,
+            votes: self.tally(candidate).unwrap_or_default(),
+        });
+        let excluded = self.excluded.iter().map(|candidate| RoundEvent::Excluded {
+            candidate,
+            votes: self.tally(candidate).unwrap_or_default(),
+        });
+        let transfers = self
+            .transfers
+            .iter()
+            .map(|transfer| RoundEvent::Transferred {
+                source: &transfer.source,
+                destination: transfer.destination.as_ref(),
+                votes: transfer.votes,
+            });
+        elected.chain(excluded).chain(transfers).collect()
+    }
+
+    /// Votes which had no further preferences to transfer to during this stage.
+    pub fn exhausted(&self) -> f64 {
+        self.transfers
+            .iter()
+            .filter(|transfer| transfer.destination.is_none())
+            .map(|transfer| transfer.votes)
+            .sum()
+    }
+}
+
+/// Something which happened during a stage of the count.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RoundEvent<'r> {
+    /// A candidate reached the quota and was elected.
+    Elected {
+        /// The candidate elected.
+        candidate: &'r Candidate,
+        /// The votes they held when they were elected.
+        votes: f64,
+    },
+    /// A candidate had the fewest votes and was excluded.
+    Excluded {
+        /// The candidate excluded.
+        candidate: &'r Candidate,
+        /// The votes they held when they were excluded.
+        votes: f64,
+    },
+    /// Votes were moved from an elected or excluded candidate.
+    Transferred {
+        /// The candidate the votes came from.
+        source: &'r Candidate,
+        /// The candidate the votes went to, or `None` if they were exhausted.
+        destination: Option<&'r Candidate>,
+        /// The number of votes moved.
+        votes: f64,
+    },
+}
+
+impl fmt::Display for RoundEvent<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RoundEvent::Elected { candidate, votes } => {
+                write!(f, "{} elected with {} votes", candidate, votes)
+            }
+            RoundEvent::Excluded { candidate, votes } => {
+                write!(f, "{} excluded with {} votes", candidate, votes)
+            }
+            RoundEvent::Transferred {
+                source,
+                destination: Some(destination),
+                votes,
+            } => write!(
+                f,
+                "{} votes transferred from {} to {}",
+                votes, source, destination
+            ),
+            RoundEvent::Transferred {
+                source,
+                destination: None,
+                votes,
+            } => write!(f, "{} votes from {} exhausted", votes, source),
+        }
+    }
+}
+
+/// Votes moved from one candidate to another in a single stage of the count.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transfer {
+    pub(crate) source: Candidate,
+    pub(crate) destination: Option<Candidate>,
+    pub(crate) votes: f64,
+}
+
+impl Transfer {
+    /// The elected or excluded candidate the votes were transferred from.
+    pub fn source(&self) -> &Candidate {
+        &self.source
+    }
+
+    /// The candidate the votes were transferred to, or `None` if the votes were exhausted.
+    pub fn destination(&self) -> Option<&Candidate> {
+        self.destination.as_ref()
+    }
+
+    /// The number of votes transferred.
+    pub fn votes(&self) -> f64 {
+        self.votes
+    }
+}
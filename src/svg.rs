@@ -0,0 +1,459 @@
+/* good_stv - a good single transferable vote utility.
+ * Copyright (C) 2019 good_stv authors
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>. */
+
+//! Drawing the count as SVG images, which can be viewed in a browser or put on slides without
+//! any other software.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::Write;
+
+use failure::*;
+
+use crate::count_sheet::format_votes;
+use crate::html::escape;
+use crate::{CountSheet, Counter, ElectionResults, Round};
+
+const COLOURS: [&str; 10] = [
+    "#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b", "#e377c2", "#7f7f7f",
+    "#bcbd22", "#17becf",
+];
+const EXHAUSTED_COLOUR: &str = "#bbbbbb";
+const FONT: &str = "font-family=\"sans-serif\" font-size=\"12\"";
+
+const SANKEY_COLUMN_WIDTH: f64 = 180.0;
+const SANKEY_NODE_WIDTH: f64 = 12.0;
+const SANKEY_HEIGHT: f64 = 500.0;
+// With many candidates the gaps between bars would leave no room for the bars themselves, so the
+// chart grows to keep at least this much height for the votes.
+const SANKEY_MIN_VOTES_HEIGHT: f64 = 300.0;
+const SANKEY_GAP: f64 = 10.0;
+const SANKEY_MARGIN: f64 = 30.0;
+const SANKEY_LABEL_WIDTH: f64 = 160.0;
+
+const BAR_CHART_WIDTH: f64 = 800.0;
+const BAR_HEIGHT: f64 = 28.0;
+const BAR_GAP: f64 = 12.0;
+const BAR_LABEL_WIDTH: f64 = 160.0;
+const BAR_MARGIN: f64 = 20.0;
+const BAR_TITLE_HEIGHT: f64 = 40.0;
+
+impl Counter<'_> {
+    /// Draw where the votes went at each stage of the count as a Sankey diagram.
+    ///
+    /// Each stage is a column with a bar for every candidate holding votes, and one for exhausted
+    /// votes, sized by their total. Bands between the columns show the votes each candidate kept
+    /// and the votes transferred from elected and excluded candidates.
+    pub fn write_sankey_svg<W: Write>(
+        &self,
+        results: &ElectionResults,
+        mut writer: W,
+    ) -> Result<(), Error> {
+        let sheet = CountSheet::new(self, results);
+        let mut svg = String::new();
+        write_sankey(&mut svg, &sheet, results, self.ballot_box().candidates())?;
+        writer
+            .write_all(svg.as_bytes())
+            .context("Error writing SVG.")?;
+        Ok(())
+    }
+
+    /// Draw the tallies at the start of a stage of the count as a horizontal bar chart, with a
+    /// line marking the quota.
+    ///
+    /// Candidates elected at the stage are drawn in bold, and the candidate excluded is greyed
+    /// out. The chart is sized to be shown as a slide.
+    pub fn write_round_svg<W: Write>(&self, round: &Round, mut writer: W) -> Result<(), Error> {
+        let mut svg = String::new();
+        write_bar_chart(&mut svg, self, round)?;
+        writer
+            .write_all(svg.as_bytes())
+            .context("Error writing SVG.")?;
+        Ok(())
+    }
+}
+
+fn write_bar_chart(svg: &mut String, counter: &Counter, round: &Round) -> Result<(), Error> {
+    let ballot_box = counter.ballot_box();
+    let precision = counter.config().precision;
+    let quota = counter.quota();
+    let largest = round
+        .tallies()
+        .iter()
+        .map(|&(_, votes)| votes)
+        .fold(quota, f64::max);
+    let bars_left = BAR_MARGIN + BAR_LABEL_WIDTH;
+    // Leave room after the longest bar for its total.
+    let bars_width = BAR_CHART_WIDTH - bars_left - BAR_MARGIN - 60.0;
+    let scale = if largest > 0.0 {
+        bars_width / largest
+    } else {
+        0.0
+    };
+    let height =
+        BAR_TITLE_HEIGHT + round.tallies().len() as f64 * (BAR_HEIGHT + BAR_GAP) + 2.0 * BAR_MARGIN;
+
+    writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" \
+         viewBox=\"0 0 {0} {1}\">",
+        BAR_CHART_WIDTH, height
+    )?;
+    writeln!(svg, "<rect width=\"100%\" height=\"100%\" fill=\"white\"/>")?;
+    let mut title = format!("Stage {}", round.number());
+    if let Some(excluded) = round.excluded() {
+        write!(title, ": {} excluded", ballot_box.display_name(excluded))?;
+    } else if !round.elected().is_empty() {
+        let elected: Vec<&str> = round
+            .elected()
+            .iter()
+            .map(|name| ballot_box.display_name(name))
+            .collect();
+        write!(title, ": {} elected", elected.join(", "))?;
+    }
+    writeln!(
+        svg,
+        "<text x=\"{}\" y=\"{}\" font-family=\"sans-serif\" font-size=\"20\" \
+         font-weight=\"bold\">{}</text>",
+        BAR_MARGIN,
+        BAR_MARGIN + 16.0,
+        escape(&title)
+    )?;
+
+    let candidates = ballot_box.candidates();
+    for (i, (name, votes)) in round.tallies().iter().enumerate() {
+        let top = BAR_MARGIN + BAR_TITLE_HEIGHT + i as f64 * (BAR_HEIGHT + BAR_GAP);
+        let middle = top + BAR_HEIGHT / 2.0;
+        let row = candidates.iter().position(|c| c == name).unwrap_or(i);
+        let elected = round.elected().contains(name);
+        let excluded = round.excluded() == Some(name);
+        let colour = if excluded {
+            EXHAUSTED_COLOUR
+        } else {
+            COLOURS[row % COLOURS.len()]
+        };
+        let weight = if elected { " font-weight=\"bold\"" } else { "" };
+        writeln!(
+            svg,
+            "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"end\" dominant-baseline=\"middle\" \
+             {}{}>{}</text>",
+            bars_left - 8.0,
+            middle,
+            FONT,
+            weight,
+            escape(ballot_box.display_name(name))
+        )?;
+        writeln!(
+            svg,
+            "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{}\" fill=\"{}\"/>",
+            bars_left,
+            top,
+            votes * scale,
+            BAR_HEIGHT,
+            colour
+        )?;
+        writeln!(
+            svg,
+            "<text x=\"{:.1}\" y=\"{:.1}\" dominant-baseline=\"middle\" {}{}>{}</text>",
+            bars_left + votes * scale + 6.0,
+            middle,
+            FONT,
+            weight,
+            format_votes(*votes, precision)
+        )?;
+    }
+
+    let quota_x = bars_left + quota * scale;
+    writeln!(
+        svg,
+        "<line x1=\"{0:.1}\" y1=\"{1:.1}\" x2=\"{0:.1}\" y2=\"{2:.1}\" stroke=\"black\" \
+         stroke-dasharray=\"6 4\"/>",
+        quota_x,
+        BAR_MARGIN + BAR_TITLE_HEIGHT - 6.0,
+        height - BAR_MARGIN
+    )?;
+    writeln!(
+        svg,
+        "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\" {}>Quota {}</text>",
+        quota_x,
+        BAR_MARGIN + BAR_TITLE_HEIGHT - 10.0,
+        FONT,
+        format_votes(quota, precision)
+    )?;
+
+    writeln!(svg, "</svg>")?;
+    Ok(())
+}
+
+// A bar in a column of the Sankey diagram, filled from the top by the bands leaving it and
+// arriving at it.
+struct Node {
+    top: f64,
+    out_offset: f64,
+    in_offset: f64,
+}
+
+fn write_sankey(
+    svg: &mut String,
+    sheet: &CountSheet,
+    results: &ElectionResults,
+    candidates: &[String],
+) -> Result<(), Error> {
+    // The candidates come first in the count sheet, then the non-transferable votes.
+    let rows = &sheet.rows()[..candidates.len() + 1];
+    let exhausted_row = candidates.len();
+    let num_columns = results.rounds().len() + 1;
+    let totals = |column: usize| -> Vec<f64> {
+        rows.iter()
+            .map(|row| row.cells()[column * 2].unwrap_or_default())
+            .collect()
+    };
+    let largest_total = (0..num_columns)
+        .map(|column| totals(column).iter().sum::<f64>())
+        .fold(0.0, f64::max);
+    let gaps = SANKEY_GAP * candidates.len() as f64;
+    let chart_height = SANKEY_HEIGHT.max(gaps + SANKEY_MIN_VOTES_HEIGHT);
+    let scale = if largest_total > 0.0 {
+        (chart_height - gaps) / largest_total
+    } else {
+        0.0
+    };
+    let index: HashMap<&str, usize> = candidates
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (name.as_str(), i))
+        .collect();
+    let colour = |row: usize| {
+        if row == exhausted_row {
+            EXHAUSTED_COLOUR
+        } else {
+            COLOURS[row % COLOURS.len()]
+        }
+    };
+    let x = |column: usize| SANKEY_MARGIN + column as f64 * SANKEY_COLUMN_WIDTH;
+
+    let width = x(num_columns - 1) + SANKEY_NODE_WIDTH + SANKEY_LABEL_WIDTH;
+    let height = chart_height + 2.0 * SANKEY_MARGIN;
+    writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" \
+         viewBox=\"0 0 {0} {1}\">",
+        width, height
+    )?;
+    writeln!(svg, "<rect width=\"100%\" height=\"100%\" fill=\"white\"/>")?;
+
+    // Lay out the bars in each column from the top, leaving out anyone with no votes.
+    let mut columns: Vec<HashMap<usize, Node>> = Vec::with_capacity(num_columns);
+    for column in 0..num_columns {
+        let mut nodes = HashMap::new();
+        let mut top = SANKEY_MARGIN;
+        for (row, votes) in totals(column).into_iter().enumerate() {
+            if votes <= 0.0 {
+                continue;
+            }
+            nodes.insert(
+                row,
+                Node {
+                    top,
+                    out_offset: 0.0,
+                    in_offset: 0.0,
+                },
+            );
+            top += votes * scale + SANKEY_GAP;
+        }
+        columns.push(nodes);
+    }
+
+    // The bands between one column and the next: the votes each candidate kept, and the votes
+    // transferred during the stage.
+    for (column, round) in results.rounds().iter().enumerate() {
+        let mut bands: Vec<(usize, usize, f64)> = Vec::new();
+        let mut kept = totals(column);
+        for transfer in round.transfers() {
+            let source = index[transfer.source().as_str()];
+            let destination = transfer
+                .destination()
+                .map_or(exhausted_row, |name| index[name.as_str()]);
+            kept[source] -= transfer.votes();
+            bands.push((source, destination, transfer.votes()));
+        }
+        bands.extend(
+            kept.into_iter()
+                .enumerate()
+                .map(|(row, votes)| (row, row, votes)),
+        );
+        bands.retain(|&(_, _, votes)| votes * scale > 0.01);
+
+        // Bands leave each bar in the order of their destination, and arrive in the order of
+        // their source, so that they cross as little as possible.
+        bands.sort_by_key(|&(source, destination, _)| (source, destination));
+        let mut out_tops = Vec::with_capacity(bands.len());
+        for &(source, _, votes) in &bands {
+            let node = columns[column]
+                .get_mut(&source)
+                .expect("band leaves a bar with votes");
+            out_tops.push(node.top + node.out_offset);
+            node.out_offset += votes * scale;
+        }
+        let mut order: Vec<usize> = (0..bands.len()).collect();
+        order.sort_by_key(|&i| (bands[i].1, bands[i].0));
+        let mut in_tops = vec![0.0; bands.len()];
+        for i in order {
+            let (_, destination, votes) = bands[i];
+            let node = columns[column + 1]
+                .get_mut(&destination)
+                .expect("band arrives at a bar with votes");
+            in_tops[i] = node.top + node.in_offset;
+            node.in_offset += votes * scale;
+        }
+
+        let x0 = x(column) + SANKEY_NODE_WIDTH;
+        let x1 = x(column + 1);
+        let middle = (x0 + x1) / 2.0;
+        for (i, &(source, _, votes)) in bands.iter().enumerate() {
+            let (y0, y1, thickness) = (out_tops[i], in_tops[i], votes * scale);
+            writeln!(
+                svg,
+                "<path d=\"M{x0:.1},{y0:.1} C{m:.1},{y0:.1} {m:.1},{y1:.1} {x1:.1},{y1:.1} \
+                 L{x1:.1},{y1b:.1} C{m:.1},{y1b:.1} {m:.1},{y0b:.1} {x0:.1},{y0b:.1} Z\" \
+                 fill=\"{colour}\" fill-opacity=\"0.35\"/>",
+                x0 = x0,
+                x1 = x1,
+                m = middle,
+                y0 = y0,
+                y1 = y1,
+                y0b = y0 + thickness,
+                y1b = y1 + thickness,
+                colour = colour(source),
+            )?;
+        }
+    }
+
+    for (column, nodes) in columns.iter().enumerate() {
+        let heading = if column == 0 {
+            "First preferences".to_owned()
+        } else {
+            format!("After stage {}", column)
+        };
+        writeln!(
+            svg,
+            "<text x=\"{:.1}\" y=\"{:.1}\" {}>{}</text>",
+            x(column),
+            SANKEY_MARGIN - 12.0,
+            FONT,
+            heading
+        )?;
+        let column_totals = totals(column);
+        let mut rows: Vec<_> = nodes.iter().collect();
+        rows.sort_by_key(|&(&row, _)| row);
+        for (&row, node) in rows {
+            let votes = column_totals[row];
+            writeln!(
+                svg,
+                "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{}\" height=\"{:.1}\" fill=\"{}\"/>",
+                x(column),
+                node.top,
+                SANKEY_NODE_WIDTH,
+                votes * scale,
+                colour(row)
+            )?;
+            writeln!(
+                svg,
+                "<text x=\"{:.1}\" y=\"{:.1}\" dominant-baseline=\"middle\" {}>{} ({})</text>",
+                x(column) + SANKEY_NODE_WIDTH + 4.0,
+                node.top + votes * scale / 2.0,
+                FONT,
+                escape(sheet.rows()[row].label()),
+                sheet.format_votes(votes)
+            )?;
+        }
+    }
+
+    writeln!(svg, "</svg>")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Election, ElectionConfig, Method};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_write_sankey_svg() {
+        let test_csv = "a,b,c,d\na,b\na,b\na,c\na\nb,c\nc\nc\nd,c\n";
+        let config = ElectionConfig::new(2).method(Method::Gregory).seed(0);
+        let election = Election::from_reader(Cursor::new(test_csv), config).unwrap();
+        let counter = election.counter();
+        let results = counter.results().unwrap();
+        let mut svg = Vec::new();
+        counter.write_sankey_svg(&results, &mut svg).unwrap();
+        let svg = String::from_utf8(svg).unwrap();
+
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.ends_with("</svg>\n"));
+        assert!(svg.contains(">a (4)</text>"));
+        assert!(svg.contains(">Non-transferable (0.47)</text>"));
+        // Bars for everyone holding votes after each stage, and a band for each transfer and for
+        // the votes each of them kept.
+        assert_eq!(4 + 5 + 4 + 4, svg.matches("<rect x=").count());
+        assert_eq!(
+            results.flows().len() + 4 + 4 + 4,
+            svg.matches("<path").count()
+        );
+    }
+
+    #[test]
+    fn test_write_sankey_svg_many_candidates() {
+        let names: Vec<String> = (0..60).map(|i| format!("c{}", i)).collect();
+        let mut test_csv = names.join(",") + "\n";
+        test_csv += &"c0\n".repeat(61);
+        for name in &names[1..] {
+            test_csv += &format!("{}\n", name);
+        }
+        let config = ElectionConfig::new(1).method(Method::Gregory).seed(0);
+        let election = Election::from_reader(Cursor::new(test_csv), config).unwrap();
+        let counter = election.counter();
+        let results = counter.results().unwrap();
+        let mut svg = Vec::new();
+        counter.write_sankey_svg(&results, &mut svg).unwrap();
+        let svg = String::from_utf8(svg).unwrap();
+
+        assert!(svg.contains(">c0 (61)</text>"));
+        assert!(!svg.contains("height=\"-"));
+        assert!(svg.contains("height=\"960\""));
+    }
+
+    #[test]
+    fn test_write_round_svg() {
+        let test_csv = "a,b,c,d\na,b\na,b\na,c\na\nb,c\nc\nc\nd,c\n";
+        let config = ElectionConfig::new(2).method(Method::Gregory).seed(0);
+        let election = Election::from_reader(Cursor::new(test_csv), config).unwrap();
+        let counter = election.counter();
+        let results = counter.results().unwrap();
+        let mut svg = Vec::new();
+        counter
+            .write_round_svg(&results.rounds()[1], &mut svg)
+            .unwrap();
+        let svg = String::from_utf8(svg).unwrap();
+
+        assert!(svg.contains(">Stage 2: d excluded</text>"));
+        assert!(svg.contains(">Quota 3</text>"));
+        assert!(svg.contains(">2.25</text>"));
+        // The background, and a bar for each continuing candidate.
+        assert_eq!(1 + 3, svg.matches("<rect").count());
+        assert_eq!(1, svg.matches("<line").count());
+    }
+}